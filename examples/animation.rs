@@ -0,0 +1,52 @@
+use skia_vulkan_gl_renderer::{skia_safe, winit, PaintError, WindowRenderer};
+use std::time::Instant;
+
+pub fn main() {
+    let event_loop = winit::event_loop::EventLoop::new();
+
+    let renderer = WindowRenderer::builder()
+        .title("animation")
+        .inner_size(winit::dpi::LogicalSize::new(800, 600))
+        .build(&event_loop)
+        .unwrap();
+    renderer.start_continuous_redraw();
+
+    let start = Instant::now();
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = winit::event_loop::ControlFlow::Poll;
+        match event {
+            winit::event::Event::WindowEvent {
+                event: winit::event::WindowEvent::CloseRequested,
+                ..
+            } => {
+                *control_flow = winit::event_loop::ControlFlow::Exit;
+            }
+            winit::event::Event::WindowEvent { event, .. } => {
+                renderer.handle_window_event(&event);
+            }
+            winit::event::Event::RedrawRequested(_) => match renderer.paint(|canvas| {
+                canvas.clear(skia_safe::Color::from_argb(255, 255, 255, 255));
+
+                let angle = start.elapsed().as_secs_f32() * 90.0;
+                let mut paint = skia_safe::Paint::default();
+                paint.set_anti_alias(true);
+                paint.set_color(skia_safe::Color::from_argb(255, 60, 120, 220));
+
+                canvas.save();
+                canvas.translate((400.0, 300.0));
+                canvas.rotate(angle, None);
+                canvas.draw_rect(
+                    skia_safe::Rect::from_xywh(-50.0, -50.0, 100.0, 100.0),
+                    &paint,
+                );
+                canvas.restore();
+            }) {
+                Ok(()) => {}
+                Err(PaintError::SurfaceOutOfDate) => renderer.resize(renderer.inner_size()),
+                Err(e) if e.is_recoverable() => eprintln!("Error painting a frame: {}", e),
+                Err(e) => panic!("Error painting a frame: {}", e),
+            },
+            _ => {}
+        }
+    })
+}
@@ -1,10 +1,12 @@
-use skia_vulkan_gl_renderer::{skia_safe, winit, WindowRenderer};
+use skia_vulkan_gl_renderer::{skia_safe, winit, PaintError, WindowRenderer};
 pub fn main() {
     let event_loop = winit::event_loop::EventLoop::new();
 
-    let window_size = winit::dpi::LogicalSize::new(800, 600);
-    let window_builder = winit::window::WindowBuilder::new().with_inner_size(window_size);
-    let renderer = WindowRenderer::new(window_builder, &event_loop);
+    let renderer = WindowRenderer::builder()
+        .title("basic")
+        .inner_size(winit::dpi::LogicalSize::new(800, 600))
+        .build(&event_loop)
+        .unwrap();
 
     event_loop.run(move |event, _, control_flow| match event {
         winit::event::Event::WindowEvent {
@@ -13,11 +15,17 @@ pub fn main() {
         } => {
             *control_flow = winit::event_loop::ControlFlow::Exit;
         }
-        winit::event::Event::RedrawRequested(_) => renderer
-            .paint(|canvas| {
-                canvas.clear(skia_safe::Color::from_argb(255, 255, 255, 255));
-            })
-            .unwrap(),
+        winit::event::Event::WindowEvent { event, .. } => {
+            renderer.handle_window_event(&event);
+        }
+        winit::event::Event::RedrawRequested(_) => match renderer.paint(|canvas| {
+            canvas.clear(skia_safe::Color::from_argb(255, 255, 255, 255));
+        }) {
+            Ok(()) => {}
+            Err(PaintError::SurfaceOutOfDate) => renderer.resize(renderer.inner_size()),
+            Err(e) if e.is_recoverable() => eprintln!("Error painting a frame: {}", e),
+            Err(e) => panic!("Error painting a frame: {}", e),
+        },
         _ => {}
     })
 }
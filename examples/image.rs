@@ -0,0 +1,60 @@
+use skia_vulkan_gl_renderer::{skia_safe, winit, PaintError, WindowRenderer};
+use std::{env, fs, process};
+
+pub fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: cargo run --example image -- <image.png>");
+            process::exit(1);
+        }
+    };
+    let bytes = fs::read(&path).unwrap();
+    let image = skia_safe::Image::from_encoded(skia_safe::Data::new_copy(&bytes), None)
+        .expect("failed to decode the image");
+
+    let event_loop = winit::event_loop::EventLoop::new();
+
+    let renderer = WindowRenderer::builder()
+        .title("image")
+        .inner_size(winit::dpi::LogicalSize::new(800, 600))
+        .build(&event_loop)
+        .unwrap();
+
+    event_loop.run(move |event, _, control_flow| match event {
+        winit::event::Event::WindowEvent {
+            event: winit::event::WindowEvent::CloseRequested,
+            ..
+        } => {
+            *control_flow = winit::event_loop::ControlFlow::Exit;
+        }
+        winit::event::Event::WindowEvent { event, .. } => {
+            renderer.handle_window_event(&event);
+        }
+        winit::event::Event::RedrawRequested(_) => match renderer.paint(|canvas| {
+            canvas.clear(skia_safe::Color::from_argb(255, 255, 255, 255));
+
+            // Scale the image down to fit the window, keeping its aspect ratio.
+            let size = renderer.logical_size();
+            let scale = (size.width as f32 / image.width() as f32)
+                .min(size.height as f32 / image.height() as f32)
+                .min(1.0);
+            let (width, height) = (image.width() as f32 * scale, image.height() as f32 * scale);
+            let dst = skia_safe::Rect::from_xywh(
+                (size.width as f32 - width) / 2.0,
+                (size.height as f32 - height) / 2.0,
+                width,
+                height,
+            );
+            let mut paint = skia_safe::Paint::default();
+            paint.set_filter_quality(skia_safe::FilterQuality::Medium);
+            canvas.draw_image_rect(&image, None, dst, &paint);
+        }) {
+            Ok(()) => {}
+            Err(PaintError::SurfaceOutOfDate) => renderer.resize(renderer.inner_size()),
+            Err(e) if e.is_recoverable() => eprintln!("Error painting a frame: {}", e),
+            Err(e) => panic!("Error painting a frame: {}", e),
+        },
+        _ => {}
+    })
+}
@@ -0,0 +1,37 @@
+use skia_vulkan_gl_renderer::{skia_safe, winit, PaintError, WindowRenderer};
+
+pub fn main() {
+    let event_loop = winit::event_loop::EventLoop::new();
+
+    let renderer = WindowRenderer::builder()
+        .title("monitors")
+        .inner_size(winit::dpi::LogicalSize::new(800, 600))
+        .build(&event_loop)
+        .unwrap();
+
+    // Open on the second monitor if there is one.
+    if let Some(monitor) = renderer.available_monitors().nth(1) {
+        renderer.set_outer_position(monitor.position());
+    }
+
+    event_loop.run(move |event, _, control_flow| match event {
+        winit::event::Event::WindowEvent {
+            event: winit::event::WindowEvent::CloseRequested,
+            ..
+        } => {
+            *control_flow = winit::event_loop::ControlFlow::Exit;
+        }
+        winit::event::Event::WindowEvent { event, .. } => {
+            renderer.handle_window_event(&event);
+        }
+        winit::event::Event::RedrawRequested(_) => match renderer.paint(|canvas| {
+            canvas.clear(skia_safe::Color::from_argb(255, 255, 255, 255));
+        }) {
+            Ok(()) => {}
+            Err(PaintError::SurfaceOutOfDate) => renderer.resize(renderer.inner_size()),
+            Err(e) if e.is_recoverable() => eprintln!("Error painting a frame: {}", e),
+            Err(e) => panic!("Error painting a frame: {}", e),
+        },
+        _ => {}
+    })
+}
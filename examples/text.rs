@@ -0,0 +1,57 @@
+use skia_safe::textlayout::{FontCollection, ParagraphBuilder, ParagraphStyle, TextStyle};
+use skia_vulkan_gl_renderer::{skia_safe, winit, PaintError, WindowRenderer};
+
+const TEXT: &str = "Paragraphs wrap to the width they are laid out at, so this one reflows \
+                    whenever the window is resized. Building a paragraph shapes its text, which \
+                    is expensive, so it is built once and only laid out again on resize.";
+
+pub fn main() {
+    let event_loop = winit::event_loop::EventLoop::new();
+
+    let renderer = WindowRenderer::builder()
+        .title("text")
+        .inner_size(winit::dpi::LogicalSize::new(800, 600))
+        .build(&event_loop)
+        .unwrap();
+
+    let mut font_collection = FontCollection::new();
+    font_collection.set_default_font_manager(skia_safe::FontMgr::new(), None);
+    let mut text_style = TextStyle::new();
+    text_style.set_font_size(24.0);
+    text_style.set_color(skia_safe::Color::from_argb(255, 40, 40, 40));
+    let mut paragraph_builder = ParagraphBuilder::new(&ParagraphStyle::new(), font_collection);
+    paragraph_builder.push_style(&text_style);
+    paragraph_builder.add_text(TEXT);
+    let mut paragraph = paragraph_builder.build();
+    paragraph.layout(renderer.logical_size().width as f32 - 40.0);
+
+    let title_font = skia_safe::Font::default().with_size(36.0).unwrap();
+    let mut title_paint = skia_safe::Paint::default();
+    title_paint.set_anti_alias(true);
+    title_paint.set_color(skia_safe::Color::from_argb(255, 60, 120, 220));
+
+    event_loop.run(move |event, _, control_flow| match event {
+        winit::event::Event::WindowEvent {
+            event: winit::event::WindowEvent::CloseRequested,
+            ..
+        } => {
+            *control_flow = winit::event_loop::ControlFlow::Exit;
+        }
+        winit::event::Event::WindowEvent { event, .. } => {
+            if renderer.handle_window_event(&event) {
+                paragraph.layout(renderer.logical_size().width as f32 - 40.0);
+            }
+        }
+        winit::event::Event::RedrawRequested(_) => match renderer.paint(|canvas| {
+            canvas.clear(skia_safe::Color::from_argb(255, 255, 255, 255));
+            canvas.draw_str("text", (20.0, 56.0), &title_font, &title_paint);
+            paragraph.paint(canvas, (20.0, 80.0));
+        }) {
+            Ok(()) => {}
+            Err(PaintError::SurfaceOutOfDate) => renderer.resize(renderer.inner_size()),
+            Err(e) if e.is_recoverable() => eprintln!("Error painting a frame: {}", e),
+            Err(e) => panic!("Error painting a frame: {}", e),
+        },
+        _ => {}
+    })
+}
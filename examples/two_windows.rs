@@ -0,0 +1,49 @@
+use skia_vulkan_gl_renderer::{skia_safe, winit, PaintError, WindowRenderer};
+pub fn main() {
+    let event_loop = winit::event_loop::EventLoop::new();
+
+    let mut renderers: Vec<_> = [("first", 255), ("second", 64)]
+        .iter()
+        .map(|&(title, shade)| {
+            let renderer = WindowRenderer::builder()
+                .title(title)
+                .inner_size(winit::dpi::LogicalSize::new(400, 300))
+                .build(&event_loop)
+                .unwrap();
+            (
+                renderer,
+                skia_safe::Color::from_argb(255, shade, shade, 255),
+            )
+        })
+        .collect();
+
+    event_loop.run(move |event, _, control_flow| match event {
+        winit::event::Event::WindowEvent {
+            event: winit::event::WindowEvent::CloseRequested,
+            window_id,
+        } => {
+            renderers.retain(|(renderer, _)| renderer.window_id() != window_id);
+            if renderers.is_empty() {
+                *control_flow = winit::event_loop::ControlFlow::Exit;
+            }
+        }
+        winit::event::Event::WindowEvent { event, window_id } => {
+            for (renderer, _) in renderers.iter().filter(|(r, _)| r.window_id() == window_id) {
+                renderer.handle_window_event(&event);
+            }
+        }
+        winit::event::Event::RedrawRequested(window_id) => {
+            for (renderer, color) in renderers.iter().filter(|(r, _)| r.window_id() == window_id) {
+                match renderer.paint(|canvas| {
+                    canvas.clear(*color);
+                }) {
+                    Ok(()) => {}
+                    Err(PaintError::SurfaceOutOfDate) => renderer.resize(renderer.inner_size()),
+                    Err(e) if e.is_recoverable() => eprintln!("Error painting a frame: {}", e),
+                    Err(e) => panic!("Error painting a frame: {}", e),
+                }
+            }
+        }
+        _ => {}
+    })
+}
@@ -0,0 +1,52 @@
+use skia_vulkan_gl_renderer::{skia_safe, winit, PaintError, WindowRenderer};
+use std::{thread, time::Duration};
+
+enum UserEvent {
+    Tick(u8),
+}
+
+pub fn main() {
+    let event_loop = winit::event_loop::EventLoop::<UserEvent>::with_user_event();
+
+    let renderer = WindowRenderer::builder()
+        .title("user_event")
+        .inner_size(winit::dpi::LogicalSize::new(800, 600))
+        .build(&event_loop)
+        .unwrap();
+
+    let proxy = event_loop.create_proxy();
+    thread::spawn(move || {
+        for shade in (0..=255).cycle() {
+            if proxy.send_event(UserEvent::Tick(shade)).is_err() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(16));
+        }
+    });
+
+    let mut shade = 0;
+    event_loop.run(move |event, _, control_flow| match event {
+        winit::event::Event::WindowEvent {
+            event: winit::event::WindowEvent::CloseRequested,
+            ..
+        } => {
+            *control_flow = winit::event_loop::ControlFlow::Exit;
+        }
+        winit::event::Event::WindowEvent { event, .. } => {
+            renderer.handle_window_event(&event);
+        }
+        winit::event::Event::UserEvent(UserEvent::Tick(next)) => {
+            shade = next;
+            renderer.request_repaint();
+        }
+        winit::event::Event::RedrawRequested(_) => match renderer.paint(|canvas| {
+            canvas.clear(skia_safe::Color::from_argb(255, shade, shade, 255));
+        }) {
+            Ok(()) => {}
+            Err(PaintError::SurfaceOutOfDate) => renderer.resize(renderer.inner_size()),
+            Err(e) if e.is_recoverable() => eprintln!("Error painting a frame: {}", e),
+            Err(e) => panic!("Error painting a frame: {}", e),
+        },
+        _ => {}
+    })
+}
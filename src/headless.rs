@@ -0,0 +1,85 @@
+use {
+    crate::{
+        save_image_png, winit, Backend, OffscreenSurface, PaintError, RendererInitError, SaveError,
+        WindowRenderer,
+    },
+    std::path::Path,
+};
+
+/// Renders into an offscreen surface without ever showing a window, for server-side rendering
+/// and golden-image tests. `paint` takes the same closure as `WindowRenderer::paint`.
+///
+/// A hidden window is still created to get a GL context, preferring OpenGL and falling back to
+/// raster, so a display server is required (X11 or Wayland on Linux). The event loop is created
+/// with winit's `new_any_thread` on Linux, the BSDs and Windows, so tests can run on the test
+/// harness's threads; on macOS it has to be created on the main thread.
+pub struct HeadlessRenderer {
+    surface: OffscreenSurface,
+    renderer: WindowRenderer,
+    _event_loop: winit::event_loop::EventLoop<()>,
+}
+impl HeadlessRenderer {
+    pub fn new(size: skia_safe::ISize) -> Result<Self, RendererInitError> {
+        let event_loop = new_event_loop();
+        let builder = WindowRenderer::builder()
+            .with_window_builder(winit::window::WindowBuilder::new().with_visible(false));
+        let renderer = builder
+            .clone()
+            .backend(Backend::OpenGl)
+            .build(&event_loop)
+            .or_else(|_| builder.backend(Backend::Raster).build(&event_loop))?;
+        let surface = renderer
+            .create_offscreen_or_raster(size)
+            .ok_or(RendererInitError::SkiaSurface)?;
+
+        Ok(Self {
+            surface,
+            renderer,
+            _event_loop: event_loop,
+        })
+    }
+    pub fn paint<F: FnOnce(&mut skia_safe::Canvas)>(&mut self, f: F) -> Result<(), PaintError> {
+        self.surface.paint(f);
+        Ok(())
+    }
+    pub fn snapshot(&mut self) -> Option<skia_safe::Image> {
+        Some(self.surface.image())
+    }
+    pub fn save_png(&mut self, path: &Path) -> Result<(), SaveError> {
+        let image = self.snapshot().ok_or(SaveError::SnapshotUnavailable)?;
+        save_image_png(&image, path)
+    }
+    pub fn size(&self) -> skia_safe::ISize {
+        self.surface.size()
+    }
+    /// The hidden window renderer that owns the GPU context.
+    pub fn renderer(&self) -> &WindowRenderer {
+        &self.renderer
+    }
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+fn new_event_loop() -> winit::event_loop::EventLoop<()> {
+    winit::platform::unix::EventLoopExtUnix::new_any_thread()
+}
+#[cfg(target_os = "windows")]
+fn new_event_loop() -> winit::event_loop::EventLoop<()> {
+    winit::platform::windows::EventLoopExtWindows::new_any_thread()
+}
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "windows"
+)))]
+fn new_event_loop() -> winit::event_loop::EventLoop<()> {
+    winit::event_loop::EventLoop::new()
+}
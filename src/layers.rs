@@ -0,0 +1,47 @@
+use crate::{OffscreenSurface, WindowRenderer};
+
+/// Identifies a layer in the `LayerStack` that created it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LayerHandle(usize);
+
+/// A set of offscreen layers, each painted independently and then blended into a canvas with
+/// `composite`, typically inside a window paint closure.
+///
+/// Layers are GPU render targets where the renderer exposes its GPU context and raster surfaces
+/// otherwise, so GPU layers must only be composited by the renderer that created them.
+#[derive(Default)]
+pub struct LayerStack {
+    layers: Vec<OffscreenSurface>,
+}
+impl LayerStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns `None` if no surface of that size could be allocated.
+    pub fn create_layer(
+        &mut self,
+        renderer: &WindowRenderer,
+        size: skia_safe::ISize,
+    ) -> Option<LayerHandle> {
+        let layer = renderer.create_offscreen_or_raster(size)?;
+        self.layers.push(layer);
+        Some(LayerHandle(self.layers.len() - 1))
+    }
+    pub fn layer_mut(&mut self, handle: LayerHandle) -> &mut OffscreenSurface {
+        &mut self.layers[handle.0]
+    }
+    /// Draw the given layers into `canvas` at the origin, in order, each with its blend mode and
+    /// an opacity from `0.0` to `1.0`.
+    pub fn composite(
+        &mut self,
+        canvas: &mut skia_safe::Canvas,
+        layers: &[(LayerHandle, skia_safe::BlendMode, f32)],
+    ) {
+        for &(handle, blend_mode, opacity) in layers {
+            let mut paint = skia_safe::Paint::default();
+            paint.set_blend_mode(blend_mode);
+            paint.set_alpha_f(opacity.clamp(0.0, 1.0));
+            self.layers[handle.0].draw_into(canvas, (0.0, 0.0), Some(&paint));
+        }
+    }
+}
@@ -1,93 +1,2208 @@
 use {
     skulpin::{
-        winit::{dpi::PhysicalSize, event_loop::EventLoopWindowTarget},
-        CoordinateSystem, CreateRendererError,
+        winit::{
+            dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize},
+            event_loop::EventLoopWindowTarget,
+        },
+        CreateRendererError,
     },
-    std::{cell::RefCell, convert::TryInto},
+    std::{
+        cell::{Cell, Ref, RefCell, RefMut},
+        collections::{HashSet, VecDeque},
+        convert::TryInto,
+        ffi::CStr,
+        fmt, io,
+        path::Path,
+        rc::{Rc, Weak},
+        sync::mpsc,
+        time::{Duration, Instant},
+    },
+};
+
+mod headless;
+mod layers;
+mod raster;
+
+pub use {
+    headless::HeadlessRenderer,
+    layers::{LayerHandle, LayerStack},
+    raster::{RasterConfig, RasterRenderer},
+    raw_window_handle, skia_safe,
+    skulpin::winit,
 };
 
-pub use {skia_safe, skulpin::winit};
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    #[default]
+    Auto,
+    Vulkan,
+    OpenGl,
+    Raster,
+}
+
+/// The units the canvas is drawn in. Both systems put the origin at the top-left of the window's
+/// inner area with +Y pointing down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoordinateSystem {
+    /// One unit is one logical pixel, ie. physical pixels divided by the window's scale factor.
+    #[default]
+    Logical,
+    /// One unit is one physical pixel of the surface.
+    Physical,
+}
+impl From<CoordinateSystem> for skulpin::CoordinateSystem {
+    fn from(coordinate_system: CoordinateSystem) -> Self {
+        match coordinate_system {
+            CoordinateSystem::Logical => Self::Logical,
+            CoordinateSystem::Physical => Self::Physical,
+        }
+    }
+}
+
+/// How presented frames are paced.
+///
+/// On Vulkan `Vsync` uses FIFO presentation and `Immediate` prefers immediate, then mailbox, then
+/// FIFO, depending on what the device supports. On OpenGL the mode sets glutin's vsync hint, which
+/// the driver may override.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PresentMode {
+    #[default]
+    Vsync,
+    Immediate,
+}
+impl PresentMode {
+    fn skulpin_priority(self) -> Vec<skulpin::PresentMode> {
+        match self {
+            Self::Vsync => vec![skulpin::PresentMode::Fifo],
+            Self::Immediate => vec![
+                skulpin::PresentMode::Immediate,
+                skulpin::PresentMode::Mailbox,
+                skulpin::PresentMode::Fifo,
+            ],
+        }
+    }
+}
+
+/// Which GPU Vulkan should pick on machines with several.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GpuPreference {
+    /// Prefer a discrete GPU.
+    HighPerformance,
+    /// Prefer an integrated GPU.
+    LowPower,
+    /// Prefer a GPU whose name contains this, ignoring case. Skulpin can only choose by device
+    /// type, so this is checked after the fact rather than used for selection.
+    ByName(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VulkanConfig {
+    pub coordinate_system: CoordinateSystem,
+    pub present_mode: PresentMode,
+    /// Load the Vulkan validation layers. If they aren't installed, construction carries on
+    /// without them.
+    pub validation: bool,
+    /// `None` leaves the choice to Skulpin. A preference that can't be met falls back to
+    /// Skulpin's choice and is recorded in `WindowRenderer::fallback_reason`.
+    pub gpu_preference: Option<GpuPreference>,
+}
+impl Default for VulkanConfig {
+    fn default() -> Self {
+        Self {
+            coordinate_system: CoordinateSystem::default(),
+            present_mode: PresentMode::default(),
+            validation: cfg!(debug_assertions),
+            gpu_preference: None,
+        }
+    }
+}
+
+/// Buffering hint for the GL context. The driver may not honor it, check
+/// `GlRenderer::pixel_format()` for what was negotiated. glutin has no way to request triple
+/// buffering, `DriverDefault` leaves that decision (and any driver setting for it) alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Buffering {
+    DriverDefault,
+    Single,
+    #[default]
+    Double,
+}
+impl Buffering {
+    fn double_buffer(self) -> Option<bool> {
+        match self {
+            Self::DriverDefault => None,
+            Self::Single => Some(false),
+            Self::Double => Some(true),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GlProfile {
+    #[default]
+    Core,
+    Compatibility,
+}
+impl GlProfile {
+    fn to_glutin(self) -> glutin::GlProfile {
+        match self {
+            Self::Core => glutin::GlProfile::Core,
+            Self::Compatibility => glutin::GlProfile::Compatibility,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlConfig {
+    pub color_bits: u8,
+    pub alpha_bits: u8,
+    /// Skia never uses the depth buffer, so it isn't described to Skia's render target and 2D
+    /// drawing is the same with or without one. It's only for interop code that draws
+    /// depth-tested geometry into the same framebuffer.
+    pub depth_bits: u8,
+    /// If no pixel format has a stencil buffer, construction retries without one. Skia then
+    /// renders complex clips without stencil, which can be slower and lower quality.
+    pub stencil_bits: u8,
+    pub msaa_samples: Option<u16>,
+    pub buffering: Buffering,
+    pub coordinate_system: CoordinateSystem,
+    pub present_mode: PresentMode,
+    /// The color type Skia renders with, which also decides the framebuffer format Skia is told
+    /// about: `RGBA8888` uses `RGBA8`, `BGRA8888` uses `BGRA8`, `RGBA1010102` uses `RGB10_A2` and
+    /// `RGBAF16` uses `RGBA16F`, for which a floating-point color buffer is requested.
+    /// `color_bits` and `alpha_bits` have to request a matching pixel format;
+    /// `WindowRendererBuilder::color_type` sets them. Other color types, and pixel formats the
+    /// driver grants with fewer color bits than the type needs or without a float buffer for
+    /// `RGBAF16`, fail construction with `RendererInitError::UnsupportedColorType` rather than
+    /// rendering garbage.
+    pub color_type: skia_safe::ColorType,
+    /// The color space the surface is tagged with. `None` draws in the device's native space
+    /// without color management. With a color space such as `ColorSpace::new_srgb()`, Skia
+    /// converts images and colors into it; the framebuffer format itself is unchanged.
+    pub color_space: Option<skia_safe::ColorSpace>,
+    pub gl_profile: GlProfile,
+    /// The desktop OpenGL `(major, minor)` version to request, or `None` for the latest the
+    /// driver offers. Skia's GL backend needs at least OpenGL 2.0 with framebuffer objects; when
+    /// the driver can't satisfy a request, construction fails with
+    /// `RendererInitError::GlCreation` or `SkiaContext`, which `Backend::Auto` falls back from.
+    pub gl_version: Option<(u8, u8)>,
+    /// Retry with the compatibility profile when creating a core profile context fails, for
+    /// legacy and remote desktop drivers that only offer the former.
+    pub compatibility_fallback: bool,
+    /// Where row 0 of the framebuffer is. The window's default framebuffer starts at the bottom
+    /// left; framebuffers from other toolkits may start at the top left. Skia flips accordingly,
+    /// so drawing and `read_pixels` rects are measured from the top left either way, and a wrong
+    /// origin shows up as upside-down output.
+    pub surface_origin: skia_safe::gpu::SurfaceOrigin,
+    /// Render into this framebuffer object instead of the one bound when the context was
+    /// created, e.g. one provided by another toolkit. It must stay the window's size.
+    pub framebuffer: Option<u32>,
+    /// Passed to Skia when creating the surface. `None` leaves the pixel geometry unknown, which
+    /// renders text with grayscale anti-aliasing; set `PixelGeometry::RGBH` or `BGRH` to match
+    /// the display's subpixel order for sharper LCD text. A mismatched order causes color
+    /// fringes, and it doesn't help on transparent surfaces or when text is rotated or scaled.
+    pub surface_props: Option<skia_safe::SurfaceProps>,
+}
+impl Default for GlConfig {
+    fn default() -> Self {
+        Self {
+            color_bits: 24,
+            alpha_bits: 8,
+            depth_bits: 0,
+            stencil_bits: 8,
+            msaa_samples: None,
+            buffering: Buffering::default(),
+            coordinate_system: CoordinateSystem::default(),
+            present_mode: PresentMode::default(),
+            color_type: skia_safe::ColorType::RGBA8888,
+            color_space: None,
+            gl_profile: GlProfile::default(),
+            gl_version: None,
+            compatibility_fallback: false,
+            surface_origin: skia_safe::gpu::SurfaceOrigin::BottomLeft,
+            framebuffer: None,
+            surface_props: None,
+        }
+    }
+}
 
+#[derive(Debug, Clone, Default)]
+pub struct WindowRendererBuilder {
+    window_builder: winit::window::WindowBuilder,
+    backend: Backend,
+    vulkan_config: VulkanConfig,
+    gl_config: GlConfig,
+    raster_config: RasterConfig,
+    clear_color: Option<skia_safe::Color>,
+    runtime_fallback: bool,
+    icon: Option<(Vec<u8>, u32, u32)>,
+    position: Option<PhysicalPosition<i32>>,
+}
+impl WindowRendererBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Start from a custom window builder, for attributes that don't have their own method here.
+    /// This replaces any window attributes set earlier, so call it first. The builder is passed
+    /// unchanged to the backend; pixel formats are configured separately through `GlConfig`.
+    pub fn with_window_builder(mut self, window_builder: winit::window::WindowBuilder) -> Self {
+        self.window_builder = window_builder;
+        self
+    }
+    pub fn title(mut self, title: &str) -> Self {
+        self.window_builder = self.window_builder.with_title(title);
+        self
+    }
+    pub fn inner_size(mut self, size: LogicalSize<u32>) -> Self {
+        self.window_builder = self.window_builder.with_inner_size(size);
+        self
+    }
+    /// Size limits are part of the window builder, so they apply whichever backend `build` ends
+    /// up with.
+    pub fn min_inner_size(mut self, size: LogicalSize<u32>) -> Self {
+        self.window_builder = self.window_builder.with_min_inner_size(size);
+        self
+    }
+    pub fn max_inner_size(mut self, size: LogicalSize<u32>) -> Self {
+        self.window_builder = self.window_builder.with_max_inner_size(size);
+        self
+    }
+    /// Set the window icon from straight RGBA8 pixels, row by row. The data is checked in
+    /// `build`, which returns `RendererInitError::Icon` if its length doesn't match the size.
+    pub fn with_icon(mut self, rgba: Vec<u8>, width: u32, height: u32) -> Self {
+        self.icon = Some((rgba, width, height));
+        self
+    }
+    /// Where to put the window's top-left corner on the desktop. winit can't set this before the
+    /// window exists, so `build` moves the window right after creating it; combine with
+    /// `visible(false)` to avoid it appearing in the default spot first. It's only a hint that
+    /// some window managers, and Wayland, ignore.
+    pub fn with_position(mut self, position: PhysicalPosition<i32>) -> Self {
+        self.position = Some(position);
+        self
+    }
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.window_builder = self.window_builder.with_resizable(resizable);
+        self
+    }
+    /// Start the window hidden to avoid showing it before anything is drawn. The recommended
+    /// startup sequence is to build with `visible(false)`, `paint` the first frame directly
+    /// rather than waiting for `RedrawRequested`, which hidden windows may never get, and then
+    /// call `WindowRenderer::show`.
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.window_builder = self.window_builder.with_visible(visible);
+        self
+    }
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+    pub fn vulkan_config(mut self, vulkan_config: VulkanConfig) -> Self {
+        self.vulkan_config = vulkan_config;
+        self
+    }
+    pub fn gl_config(mut self, gl_config: GlConfig) -> Self {
+        self.gl_config = gl_config;
+        self
+    }
+    pub fn raster_config(mut self, raster_config: RasterConfig) -> Self {
+        self.raster_config = raster_config;
+        self
+    }
+    pub fn coordinate_system(mut self, coordinate_system: CoordinateSystem) -> Self {
+        self.vulkan_config.coordinate_system = coordinate_system;
+        self.gl_config.coordinate_system = coordinate_system;
+        self.raster_config.coordinate_system = coordinate_system;
+        self
+    }
+    /// Clear the canvas to `color` before every paint closure runs. Without this the canvas is
+    /// left as it is.
+    pub fn clear_color(mut self, color: skia_safe::Color) -> Self {
+        self.clear_color = Some(color);
+        self
+    }
+    /// Make the window transparent where nothing opaque is drawn: sets winit's transparency hint,
+    /// requests an 8-bit alpha channel from OpenGL unless `color_type` already asked for one, and
+    /// clears to `Color::TRANSPARENT` unless a clear color was already set. The GL surface is
+    /// premultiplied, so it keeps the alpha.
+    ///
+    /// Whether the desktop shows through depends on the platform and its compositor: X11 needs a
+    /// running compositor, and Skulpin's swapchain and the raster backend are always opaque.
+    ///
+    /// Skia only renders into premultiplied surfaces; `from_backend_render_target` has no alpha
+    /// type to choose, so there is no unpremultiplied option on any backend. Images with straight
+    /// alpha blend correctly when they're created with `AlphaType::Unpremul`, which Skia
+    /// converts while drawing.
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.window_builder = self.window_builder.with_transparent(transparent);
+        if transparent {
+            if self.gl_config.alpha_bits == 0 {
+                self.gl_config.alpha_bits = 8;
+            }
+            self.clear_color
+                .get_or_insert(skia_safe::Color::TRANSPARENT);
+        }
+        self
+    }
+    pub fn preferred_gpu(mut self, preference: GpuPreference) -> Self {
+        self.vulkan_config.gpu_preference = Some(preference);
+        self
+    }
+    pub fn validation(mut self, validation: bool) -> Self {
+        self.vulkan_config.validation = validation;
+        self
+    }
+    /// Tag the OpenGL surface with `color_space`, so Skia converts colors and images into it.
+    /// The Vulkan and raster backends ignore it; see `GlConfig::color_space`.
+    pub fn color_space(mut self, color_space: skia_safe::ColorSpace) -> Self {
+        self.gl_config.color_space = Some(color_space);
+        self
+    }
+    /// Create the OpenGL surface with `surface_props`, e.g. to set the pixel geometry for LCD
+    /// text. The Vulkan and raster backends ignore them; see `GlConfig::surface_props`.
+    pub fn surface_props(mut self, surface_props: skia_safe::SurfaceProps) -> Self {
+        self.gl_config.surface_props = Some(surface_props);
+        self
+    }
+    /// Also requests matching color and alpha bits on OpenGL; see `GlConfig::color_type`.
+    pub fn color_type(mut self, color_type: skia_safe::ColorType) -> Self {
+        if let Some((_, color_bits, alpha_bits)) = gl_format(color_type) {
+            self.gl_config.color_bits = color_bits;
+            self.gl_config.alpha_bits = alpha_bits;
+        }
+        self.gl_config.color_type = color_type;
+        self
+    }
+    /// Draw straight into the front buffer on OpenGL: requests a single-buffered context and
+    /// makes `paint` call `glFlush` instead of swapping. Partially drawn frames can show, and
+    /// many drivers and compositors (Wayland, macOS) only offer double buffering; when they do,
+    /// `paint` swaps as usual. See `GlRenderer::pixel_format()` for what was negotiated.
+    pub fn single_buffer(mut self) -> Self {
+        self.gl_config.buffering = Buffering::Single;
+        self
+    }
+    pub fn gl_profile(mut self, gl_profile: GlProfile) -> Self {
+        self.gl_config.gl_profile = gl_profile;
+        self
+    }
+    pub fn gl_version(mut self, major: u8, minor: u8) -> Self {
+        self.gl_config.gl_version = Some((major, minor));
+        self
+    }
+    pub fn present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.vulkan_config.present_mode = present_mode;
+        self.gl_config.present_mode = present_mode;
+        self
+    }
+    /// Let a Vulkan renderer switch to OpenGL once painting has failed
+    /// `RUNTIME_FALLBACK_THRESHOLD` times in a row, e.g. after `ERROR_DEVICE_LOST`. The switch
+    /// needs the event loop to open the new window, so it happens in
+    /// `WindowRenderer::fall_back_to_gl`; check `needs_runtime_fallback` after a failed paint.
+    pub fn auto_fallback_on_runtime_failure(mut self, enabled: bool) -> Self {
+        self.runtime_fallback = enabled;
+        self
+    }
+    pub fn build<E>(
+        mut self,
+        event_loop: &EventLoopWindowTarget<E>,
+    ) -> Result<WindowRenderer, RendererInitError> {
+        if let Some((rgba, width, height)) = self.icon.take() {
+            let icon = winit::window::Icon::from_rgba(rgba, width, height)
+                .map_err(RendererInitError::Icon)?;
+            self.window_builder = self.window_builder.with_window_icon(Some(icon));
+        }
+        let result = match self.backend {
+            Backend::Auto => match self.build_vulkan(event_loop) {
+                Err(RendererInitError::Vulkan(e)) => {
+                    log::warn!(
+                        "Error during skulpin renderer construction: {:?}, Using OpenGL.",
+                        e
+                    );
+                    let vulkan_reason = format!("{:?}", e);
+                    match self.build_gl(event_loop) {
+                        Err(e) if e.allows_fallback() => {
+                            log::warn!(
+                                "Error during OpenGL renderer construction: {:?}, Using raster.",
+                                e
+                            );
+                            let reason = format!("{}; {:?}", vulkan_reason, e);
+                            self.build_raster(event_loop).map(|mut renderer| {
+                                renderer.frame_state_mut().fallback_reason = Some(reason);
+                                renderer
+                            })
+                        }
+                        result => result.map(|mut renderer| {
+                            renderer.frame_state_mut().fallback_reason = Some(vulkan_reason);
+                            renderer
+                        }),
+                    }
+                }
+                result => result,
+            },
+            Backend::Vulkan => self.build_vulkan(event_loop),
+            Backend::OpenGl => self.build_gl(event_loop),
+            Backend::Raster => self.build_raster(event_loop),
+        };
+        match result {
+            Ok(mut renderer) => {
+                if let Some(position) = self.position {
+                    renderer.window().set_outer_position(position);
+                }
+                let is_vulkan = renderer.backend() == Backend::Vulkan;
+                let frame_state = renderer.frame_state_mut();
+                frame_state.clear_color = self.clear_color;
+                frame_state.title = RefCell::new(self.window_builder.window.title.clone());
+                if self.runtime_fallback && is_vulkan {
+                    frame_state.runtime_fallback = Some(Box::new(self));
+                }
+                Ok(renderer)
+            }
+            Err(e) => {
+                log::error!("Error during renderer construction: {:?}", e);
+                Err(e)
+            }
+        }
+    }
+    fn build_vulkan<E>(
+        &self,
+        event_loop: &EventLoopWindowTarget<E>,
+    ) -> Result<WindowRenderer, RendererInitError> {
+        SkulpinRenderer::with_config(
+            self.window_builder.clone(),
+            event_loop,
+            self.vulkan_config.clone(),
+        )
+        .map(WindowRenderer::Skulpin)
+    }
+    fn build_gl<E>(
+        &self,
+        event_loop: &EventLoopWindowTarget<E>,
+    ) -> Result<WindowRenderer, RendererInitError> {
+        GlRenderer::with_config(
+            self.window_builder.clone(),
+            event_loop,
+            self.gl_config.clone(),
+        )
+        .map(WindowRenderer::Gl)
+    }
+    fn build_raster<E>(
+        &self,
+        event_loop: &EventLoopWindowTarget<E>,
+    ) -> Result<WindowRenderer, RendererInitError> {
+        RasterRenderer::with_config(self.window_builder.clone(), event_loop, self.raster_config)
+            .map(WindowRenderer::Raster)
+    }
+}
+
+/// Open a window of `size` and call `draw` for every frame until it's closed, for programs that
+/// don't need their own event loop. Frames are drawn continuously with `ControlFlow::Poll`,
+/// resizes are handled, and Vulkan falls back to OpenGL if painting keeps failing.
+///
+/// # Panics
+///
+/// If the renderer can't be created with any backend.
+pub fn run_windowed(
+    size: LogicalSize<u32>,
+    mut draw: impl FnMut(&mut skia_safe::Canvas) + 'static,
+) -> ! {
+    let event_loop = winit::event_loop::EventLoop::new();
+    let mut renderer = WindowRenderer::builder()
+        .inner_size(size)
+        .auto_fallback_on_runtime_failure(true)
+        .build(&event_loop)
+        .expect("Error creating the renderer");
+    renderer.start_continuous_redraw();
+
+    event_loop.run(move |event, window_target, control_flow| match event {
+        winit::event::Event::NewEvents(winit::event::StartCause::Init) => {
+            *control_flow = winit::event_loop::ControlFlow::Poll
+        }
+        winit::event::Event::WindowEvent {
+            event: winit::event::WindowEvent::CloseRequested,
+            ..
+        } => *control_flow = winit::event_loop::ControlFlow::Exit,
+        winit::event::Event::WindowEvent { event, .. } => {
+            renderer.handle_window_event(&event);
+        }
+        winit::event::Event::RedrawRequested(_) => match renderer.paint(&mut draw) {
+            Ok(()) => {}
+            Err(PaintError::SurfaceOutOfDate) => renderer.resize(renderer.inner_size()),
+            Err(e) => {
+                log::error!("Error painting a frame: {}", e);
+                if renderer.needs_runtime_fallback()
+                    && renderer.fall_back_to_gl(window_target).is_ok()
+                {
+                    renderer.start_continuous_redraw();
+                }
+            }
+        },
+        _ => {}
+    })
+}
+
+/// The pixel formats OpenGL offers for `config` at each MSAA level from none to 16x, for picking
+/// `GlConfig::msaa_samples` before creating a renderer. Levels the driver can't provide are left
+/// out. Each probe creates and destroys a hidden window and context, which makes its context
+/// current on this thread; renderers make their own current again before drawing.
+pub fn available_gl_pixel_formats<E>(
+    event_loop: &EventLoopWindowTarget<E>,
+    config: &GlConfig,
+) -> Vec<glutin::PixelFormat> {
+    let mut formats: Vec<glutin::PixelFormat> = Vec::new();
+    for &samples in &[None, Some(2), Some(4), Some(8), Some(16)] {
+        let mut cb = glutin::ContextBuilder::new()
+            .with_depth_buffer(config.depth_bits)
+            .with_stencil_buffer(config.stencil_bits)
+            .with_pixel_format(config.color_bits, config.alpha_bits)
+            .with_float_color_buffer(config.color_type == skia_safe::ColorType::RGBAF16)
+            .with_double_buffer(config.buffering.double_buffer())
+            .with_gl_profile(config.gl_profile.to_glutin());
+        if let Some(samples) = samples {
+            cb = cb.with_multisampling(samples);
+        }
+        let window_builder = winit::window::WindowBuilder::new().with_visible(false);
+        let context = match cb.build_windowed(window_builder, event_loop) {
+            Ok(context) => context,
+            Err(_) => continue,
+        };
+        if let Ok(context) = unsafe { context.make_current() } {
+            let format = context.get_pixel_format();
+            if !formats
+                .iter()
+                .any(|other| other.multisampling == format.multisampling)
+            {
+                formats.push(format);
+            }
+        }
+    }
+    formats
+}
+
+/// The renderer only borrows the event loop during construction, so the constructors are
+/// generic over its user event type and the renderer itself isn't.
+///
+/// # Threads
+///
+/// A renderer must be created and used on the thread that runs the event loop. It is neither
+/// `Send` nor `Sync`: the OpenGL context is current only on its creating thread, the softbuffer
+/// context holds the platform's display connection, and the per-frame state uses `Cell` and
+/// `RefCell` without locking. The compiler enforces this, so there are no runtime checks; to
+/// render from another thread, send it the data to draw and paint on the event loop thread.
+///
+/// # Multiple windows
+///
+/// Several renderers can share one event loop. Route `Event::RedrawRequested(id)` and
+/// `Event::WindowEvent { window_id, .. }` to the renderer whose `window_id` matches. OpenGL
+/// renderers make their own context current before drawing, so they don't draw into each
+/// other's windows.
 pub enum WindowRenderer {
     Skulpin(SkulpinRenderer),
     Gl(GlRenderer),
+    Raster(RasterRenderer),
+}
+
+impl WindowRenderer {
+    pub fn builder() -> WindowRendererBuilder {
+        WindowRendererBuilder::new()
+    }
+    pub fn new<E>(
+        window_builder: winit::window::WindowBuilder,
+        event_loop: &EventLoopWindowTarget<E>,
+    ) -> Result<Self, RendererInitError> {
+        Self::with_backend(window_builder, event_loop, Backend::Auto)
+    }
+    /// Construct a renderer using the given backend. `Backend::Auto` tries Vulkan, then OpenGL,
+    /// then raster; any other backend returns its error instead of falling back.
+    pub fn with_backend<E>(
+        window_builder: winit::window::WindowBuilder,
+        event_loop: &EventLoopWindowTarget<E>,
+        backend: Backend,
+    ) -> Result<Self, RendererInitError> {
+        WindowRendererBuilder {
+            window_builder,
+            backend,
+            ..Default::default()
+        }
+        .build(event_loop)
+    }
+    pub fn resize(&self, size: PhysicalSize<u32>) {
+        match self {
+            Self::Skulpin(renderer) => renderer.resize(size),
+            Self::Gl(renderer) => renderer.resize(size),
+            Self::Raster(renderer) => renderer.resize(size),
+        }
+    }
+    /// Like `resize`, but reports failures instead of logging them. Sizes the surface can't have,
+    /// such as ones above the OpenGL driver's maximum texture size, return
+    /// `ResizeError::TooLarge` and keep the old surface, so paints report
+    /// `PaintError::SurfaceOutOfDate` until the window is made smaller. Skulpin manages its
+    /// swapchain itself and always returns `Ok(())`.
+    pub fn try_resize(&self, size: PhysicalSize<u32>) -> Result<(), ResizeError> {
+        match self {
+            Self::Skulpin(renderer) => {
+                renderer.resize(size);
+                Ok(())
+            }
+            Self::Gl(renderer) => renderer.try_resize(size),
+            Self::Raster(renderer) => renderer.try_resize(size),
+        }
+    }
+    /// Resize the window to `size` in logical pixels, and the surface to match it at the current
+    /// scale factor. Unlike `resize`, this changes the window itself, so don't call it from
+    /// `WindowEvent::Resized`. The window manager may apply the size later or not at all, in
+    /// which case the next paint returns `PaintError::SurfaceOutOfDate`.
+    pub fn resize_logical(&self, size: LogicalSize<u32>) {
+        self.window().set_inner_size(size);
+        self.resize(size.to_physical(self.scale_factor()))
+    }
+    pub fn paint<F: FnOnce(&mut skia_safe::Canvas)>(&self, f: F) -> Result<(), PaintError> {
+        self.paint_with_render_scale(true, f)
+    }
+    /// `paint`, optionally bypassing the render scale for drawing that resets the matrix to place
+    /// physical pixels, which the smaller scaled target would crop.
+    fn paint_with_render_scale<F: FnOnce(&mut skia_safe::Canvas)>(
+        &self,
+        render_scale: bool,
+        f: F,
+    ) -> Result<(), PaintError> {
+        let _painting = match self.start_paint() {
+            Some(painting) => painting,
+            None => return Ok(()),
+        };
+        let draw = |canvas: &mut skia_safe::Canvas| self.draw_frame(canvas, None, f);
+        let target = if render_scale {
+            self.scaled_target()
+        } else {
+            None
+        };
+        let result = match target {
+            Some(mut target) => {
+                // The content scale is applied on top by `draw_frame`.
+                let scale = self.render_scale()
+                    * canvas_scale(self.coordinate_system(), self.scale_factor(), 1.0);
+                target.paint(|canvas| {
+                    canvas.reset_matrix();
+                    canvas.scale((scale, scale));
+                    draw(canvas);
+                });
+                let image = target.image();
+                let size = self.inner_size();
+                let mut paint = skia_safe::Paint::default();
+                paint.set_filter_quality(skia_safe::FilterQuality::Low);
+                // Replace the window's previous frame rather than blending translucent pixels
+                // over it.
+                paint.set_blend_mode(skia_safe::BlendMode::Src);
+                self.paint_frame(|canvas| {
+                    canvas.reset_matrix();
+                    canvas.draw_image_rect(
+                        &image,
+                        None,
+                        skia_safe::Rect::from_wh(size.width as f32, size.height as f32),
+                        &paint,
+                    );
+                })
+            }
+            None => self.paint_frame(draw),
+        };
+        self.finish_frame(&result);
+        result
+    }
+    /// Render `paint` frames at `scale` times the window's resolution and upscale them when
+    /// presenting, to trade sharpness for speed during interactive resizes or heavy animation.
+    /// `scale` is clamped to between 0.1 and 1.0, and 1.0 restores full resolution and frees the
+    /// intermediate surface. Drawing keeps the same coordinates at any scale, as long as the
+    /// closure doesn't call `reset_matrix`.
+    ///
+    /// Only `paint` and the methods built on it are scaled; `paint_surface`, `paint_region`,
+    /// `begin_paint`, `present_image` and `apply_image_filter` always draw at full resolution.
+    /// On OpenGL the frame is drawn into an offscreen render target; Vulkan and raster use a
+    /// raster surface, which on Vulkan is uploaded every frame and may not be faster.
+    pub fn set_render_scale(&self, scale: f32) {
+        let scale = scale.clamp(0.1, 1.0);
+        let frame_state = self.frame_state();
+        frame_state.render_scale.set(Some(scale));
+        if scale >= 1.0 {
+            *frame_state.scaled_target.borrow_mut() = None;
+        }
+        self.request_repaint();
+    }
+    pub fn render_scale(&self) -> f32 {
+        self.frame_state().render_scale.get().unwrap_or(1.0)
+    }
+    /// The surface `paint` draws into below full render scale, reallocated when the window
+    /// size changes.
+    fn scaled_target(&self) -> Option<RefMut<OffscreenSurface>> {
+        let scale = self.render_scale();
+        if scale >= 1.0 {
+            return None;
+        }
+        let size = self.inner_size();
+        let size = skia_safe::ISize::new(
+            ((size.width as f32 * scale).round() as i32).max(1),
+            ((size.height as f32 * scale).round() as i32).max(1),
+        );
+        let mut target = self.frame_state().scaled_target.borrow_mut();
+        if target.as_ref().map(OffscreenSurface::size) != Some(size) {
+            *target = Some(self.create_offscreen_or_raster(size)?);
+        }
+        Some(RefMut::map(target, |target| target.as_mut().unwrap()))
+    }
+    pub fn coordinate_system(&self) -> CoordinateSystem {
+        match self {
+            Self::Skulpin(renderer) => renderer.config.coordinate_system,
+            Self::Gl(renderer) => renderer.config.coordinate_system,
+            Self::Raster(renderer) => renderer.coordinate_system(),
+        }
+    }
+    /// Like `paint`, but returns `Ok(false)` without calling `f` when called from inside another
+    /// paint's closure, e.g. by an event handler the closure triggered. Calling `paint` itself
+    /// re-entrantly panics, since the surface is already borrowed.
+    pub fn try_paint<F: FnOnce(&mut skia_safe::Canvas)>(&self, f: F) -> Result<bool, PaintError> {
+        if self.frame_state().painting.get() {
+            return Ok(false);
+        }
+        self.paint(f).map(|()| true)
+    }
+    /// Like `paint`, but hands the closure the whole `skia_safe::Surface`. Only the OpenGL and
+    /// raster backends support this; Skulpin only exposes its canvas, so on Vulkan this returns
+    /// `PaintError::SurfaceUnavailable` without calling `f`.
+    pub fn paint_surface<F: FnOnce(&mut skia_safe::Surface)>(
+        &self,
+        f: F,
+    ) -> Result<(), PaintError> {
+        let _painting = match self.start_paint() {
+            Some(painting) => painting,
+            None => return Ok(()),
+        };
+        let f = |surface: &mut skia_safe::Surface| {
+            let save_count = self.begin_drawing(surface.canvas(), None);
+            f(surface);
+            self.end_drawing(surface.canvas(), save_count);
+        };
+        let result = match self {
+            Self::Skulpin(_) => Err(PaintError::SurfaceUnavailable),
+            Self::Gl(renderer) => renderer.paint_surface(f),
+            Self::Raster(renderer) => {
+                renderer.paint_surface(f);
+                Ok(())
+            }
+        };
+        self.finish_frame(&result);
+        result
+    }
+    /// Start a frame that's drawn through the returned guard and presented when it's finished or
+    /// dropped, for drawing that can't be expressed as a single closure. Prefer
+    /// `PaintGuard::finish`, since dropping the guard can only log presentation errors. Like
+    /// `paint_surface`, this returns `PaintError::SurfaceUnavailable` on Vulkan.
+    ///
+    /// Returns `Ok(None)` without starting a frame where the other paint methods skip theirs:
+    /// while paused or minimized, or when repaints are coalesced and another paint is in progress.
+    /// Otherwise the surface stays borrowed while the guard lives, so calling other paint
+    /// methods, or anything else that draws, panics until it's gone.
+    pub fn begin_paint(&self) -> Result<Option<PaintGuard>, PaintError> {
+        if let Self::Skulpin(_) = self {
+            self.frame_state().repaint_pending.set(false);
+            return Err(PaintError::SurfaceUnavailable);
+        }
+        let painting = match self.start_paint() {
+            Some(painting) => painting,
+            None => return Ok(None),
+        };
+        let surface = match self {
+            Self::Skulpin(_) => unreachable!(),
+            Self::Gl(renderer) => renderer.begin_paint(),
+            Self::Raster(renderer) => Ok(renderer.begin_paint()),
+        };
+        let mut surface = match surface {
+            Ok(surface) => surface,
+            Err(e) => {
+                let result = Err(e);
+                self.finish_frame(&result);
+                return result.map(|()| None);
+            }
+        };
+        let save_count = self.begin_drawing(surface.canvas(), None);
+        Ok(Some(PaintGuard {
+            renderer: self,
+            surface: Some(surface),
+            save_count,
+            _painting: painting,
+        }))
+    }
+    /// Describe the OpenGL surface so a `skia_safe::DeferredDisplayListRecorder` can record
+    /// against it away from the GPU thread, with the result drawn by
+    /// `paint_deferred_display_list`. `None` on Vulkan and raster, which don't support deferred
+    /// display lists. The characterization goes stale when the window is resized.
+    pub fn characterize(&self) -> Option<skia_safe::SurfaceCharacterization> {
+        match self {
+            Self::Gl(renderer) => renderer.characterize(),
+            _ => None,
+        }
+    }
+    /// Like `paint_surface`, but draws a deferred display list recorded against `characterize`.
+    /// Returns `Ok(false)` if it was recorded for a different surface, e.g. before a resize, in
+    /// which case the frame is presented without it. On Vulkan and raster this returns
+    /// `PaintError::SurfaceUnavailable`.
+    pub fn paint_deferred_display_list(
+        &self,
+        display_list: skia_safe::DeferredDisplayList,
+    ) -> Result<bool, PaintError> {
+        if !matches!(self, Self::Gl(_)) {
+            return Err(PaintError::SurfaceUnavailable);
+        }
+        let mut drawn = false;
+        self.paint_surface(|surface| drawn = surface.draw_display_list(display_list))?;
+        Ok(drawn)
+    }
+    /// Like `paint`, but only redraws `damage`, given in physical pixels from the top left. The
+    /// canvas is clipped to it, and on OpenGL only that region is presented when the driver
+    /// supports swapping with damage; otherwise the whole buffer is swapped as usual.
+    ///
+    /// The caller is responsible for `damage` covering everything that changed since the last
+    /// frame. Pixels outside it keep whatever the back buffer held, which is only the previous
+    /// frame when the driver preserves it across swaps.
+    pub fn paint_region<F: FnOnce(&mut skia_safe::Canvas)>(
+        &self,
+        damage: skia_safe::IRect,
+        f: F,
+    ) -> Result<(), PaintError> {
+        let _painting = match self.start_paint() {
+            Some(painting) => painting,
+            None => return Ok(()),
+        };
+        let f = |canvas: &mut skia_safe::Canvas| self.draw_frame(canvas, Some(damage), f);
+        let result = match self {
+            Self::Gl(renderer) => renderer.paint_region(damage, f),
+            _ => self.paint_frame(f),
+        };
+        self.finish_frame(&result);
+        result
+    }
+    /// Minimized windows report a zero size; painting is skipped until a resize restores it.
+    fn is_minimized(&self) -> bool {
+        let size = self.inner_size();
+        size.width == 0 || size.height == 0
+    }
+    fn skips_paint(&self) -> bool {
+        self.frame_state().paused.get() || self.is_minimized()
+    }
+    /// While paused, the paint methods return `Ok(())` without drawing and continuous redraw
+    /// stops requesting repaints, e.g. to stop rendering on `WindowEvent::Focused(false)`.
+    /// Paints made while paused are dropped rather than queued, so repaint after resuming.
+    /// Resuming re-arms continuous redraw if it was started.
+    pub fn set_paused(&self, paused: bool) {
+        let frame_state = self.frame_state();
+        frame_state.paused.set(paused);
+        if !paused && frame_state.continuous_redraw.get() {
+            self.request_repaint();
+        }
+    }
+    pub fn is_paused(&self) -> bool {
+        self.frame_state().paused.get()
+    }
+    /// When enabled, `request_repaint` does nothing while a repaint is already pending, and paints
+    /// started while another is in progress return `Ok(())` without drawing, like `try_paint`.
+    /// At most one frame is queued, so producers that request repaints faster than frames can be
+    /// painted don't build up a backlog; whatever state the next paint draws wins. This trades
+    /// throughput for latency: intermediate states are never shown.
+    pub fn set_coalesce_repaints(&self, coalesce: bool) {
+        let frame_state = self.frame_state();
+        frame_state.coalesce_repaints.set(coalesce);
+        frame_state.repaint_pending.set(false);
+    }
+    /// Shared start of the paint methods. `None` when the paint is skipped, because rendering is
+    /// paused, the window is minimized, or repaints are coalesced and another paint is in
+    /// progress. Skipped paints still answer the pending repaint, so later requests aren't
+    /// coalesced away.
+    fn start_paint(&self) -> Option<PaintingGuard> {
+        let frame_state = self.frame_state();
+        frame_state.repaint_pending.set(false);
+        if self.skips_paint() {
+            return None;
+        }
+        if frame_state.coalesce_repaints.get() && frame_state.painting.get() {
+            return None;
+        }
+        let painting = PaintingGuard::new(&frame_state.painting);
+        self.notify_frame_begin();
+        Some(painting)
+    }
+    /// Clear the frame and apply the content scale before a paint's closure runs, returning the
+    /// save count for `end_drawing`. `clip` is in physical pixels from the top left.
+    fn begin_drawing(
+        &self,
+        canvas: &mut skia_safe::Canvas,
+        clip: Option<skia_safe::IRect>,
+    ) -> usize {
+        // Closures that leave saves or transforms unbalanced don't leak into the next frame.
+        let save_count = canvas.save();
+        if let Some(clip) = clip {
+            let matrix = canvas.total_matrix();
+            canvas.reset_matrix();
+            canvas.clip_irect(clip, None);
+            canvas.set_matrix(&matrix);
+        }
+        if let Some(color) = self.frame_state().clear_color {
+            canvas.clear(color);
+        }
+        let content_scale = self.content_scale();
+        canvas.scale((content_scale, content_scale));
+        save_count
+    }
+    fn end_drawing(&self, canvas: &mut skia_safe::Canvas, save_count: usize) {
+        canvas.restore_to_count(save_count);
+        self.notify_paint_end();
+    }
+    fn draw_frame<F: FnOnce(&mut skia_safe::Canvas)>(
+        &self,
+        canvas: &mut skia_safe::Canvas,
+        clip: Option<skia_safe::IRect>,
+        f: F,
+    ) {
+        let save_count = self.begin_drawing(canvas, clip);
+        f(canvas);
+        self.end_drawing(canvas, save_count);
+    }
+    /// Register `observer` to be told when each frame starts, finishes drawing and finishes
+    /// presenting, e.g. to emit profiler markers. Replaces any previous observer.
+    pub fn set_frame_observer(&self, observer: Box<dyn FrameObserver>) {
+        *self.frame_state().frame_observer.borrow_mut() = Some(observer);
+    }
+    pub fn clear_frame_observer(&self) {
+        *self.frame_state().frame_observer.borrow_mut() = None;
+    }
+    fn notify(&self, f: impl FnOnce(&dyn FrameObserver)) {
+        if let Some(observer) = &*self.frame_state().frame_observer.borrow() {
+            f(observer.as_ref());
+        }
+    }
+    fn notify_frame_begin(&self) {
+        self.frame_state().frame_open.set(true);
+        self.notify(|observer| observer.on_frame_begin());
+    }
+    /// Frames that fail or are abandoned before their closure runs still end, so observers
+    /// always see `on_frame_begin` and `on_paint_end` in pairs.
+    fn notify_paint_end(&self) {
+        if self.frame_state().frame_open.replace(false) {
+            self.notify(|observer| observer.on_paint_end());
+        }
+    }
+    /// Run `f` and flush its drawing to the GPU like `paint`, but don't present the frame, for
+    /// measuring draw cost without presentation and vsync. The frame never becomes visible, and
+    /// continuous redraw and the frame rate limit aren't applied.
+    ///
+    /// Skulpin can only draw as part of presenting, so on Vulkan this returns
+    /// `PaintError::SurfaceUnavailable` without calling `f`.
+    pub fn paint_no_present<F: FnOnce(&mut skia_safe::Canvas)>(
+        &self,
+        f: F,
+    ) -> Result<(), PaintError> {
+        let _painting = match self.start_paint() {
+            Some(painting) => painting,
+            None => return Ok(()),
+        };
+        let f = |canvas: &mut skia_safe::Canvas| self.draw_frame(canvas, None, f);
+        let result = match self {
+            Self::Skulpin(_) => Err(PaintError::SurfaceUnavailable),
+            Self::Gl(renderer) => renderer.paint_no_present(f),
+            Self::Raster(renderer) => {
+                renderer.paint_no_present(f);
+                Ok(())
+            }
+        };
+        self.notify_paint_end();
+        result
+    }
+    /// Present whatever was last rendered without drawing or flushing Skia, for when other code
+    /// renders into the OpenGL context. The caller is responsible for having rendered and
+    /// flushed a frame first, otherwise the contents are undefined. On raster this copies the
+    /// surface to the window again. Skulpin only presents as part of drawing, so on Vulkan this
+    /// returns `PaintError::SurfaceUnavailable`.
+    pub fn present(&self) -> Result<(), PaintError> {
+        self.frame_state().repaint_pending.set(false);
+        if self.skips_paint() {
+            return Ok(());
+        }
+        if let Self::Skulpin(_) = self {
+            return Err(PaintError::SurfaceUnavailable);
+        }
+        // There's nothing to draw, so the frame's drawing ends as soon as it begins.
+        self.notify_frame_begin();
+        self.notify_paint_end();
+        let result = match self {
+            Self::Skulpin(_) => unreachable!(),
+            Self::Gl(renderer) => renderer.swap_buffers(),
+            Self::Raster(renderer) => {
+                renderer.paint_surface(|_| {});
+                Ok(())
+            }
+        };
+        self.finish_frame(&result);
+        result
+    }
+    fn finish_frame(&self, result: &Result<(), PaintError>) {
+        self.notify_paint_end();
+        self.notify(|observer| observer.on_present_end());
+        let frame_state = self.frame_state();
+        if result.is_ok() {
+            frame_state
+                .frame_count
+                .set(frame_state.frame_count.get() + 1);
+            let mut recent_frames = frame_state.recent_frames.borrow_mut();
+            if recent_frames.len() == FPS_WINDOW {
+                recent_frames.pop_front();
+            }
+            recent_frames.push_back(Instant::now());
+        }
+        if frame_state.continuous_redraw.get() {
+            match frame_state.frame_rate_limit.get() {
+                Some(fps) => {
+                    let interval = Duration::from_secs(1) / fps.max(1);
+                    frame_state.next_frame.set(Some(Instant::now() + interval));
+                }
+                None => self.request_repaint(),
+            }
+        }
+    }
+    /// Pace continuous redraw under `set_frame_rate_limit`: sets `control_flow` to wait until the
+    /// next frame is due, and requests the repaint once it is. Call it on
+    /// `Event::MainEventsCleared`; it does nothing without a frame rate limit.
+    pub fn wait_for_next_frame(&self, control_flow: &mut winit::event_loop::ControlFlow) {
+        let frame_state = self.frame_state();
+        let deadline = match frame_state.next_frame.get() {
+            Some(deadline) => deadline,
+            None => return,
+        };
+        if Instant::now() < deadline {
+            *control_flow = winit::event_loop::ControlFlow::WaitUntil(deadline);
+            return;
+        }
+        frame_state.next_frame.set(None);
+        *control_flow = winit::event_loop::ControlFlow::Poll;
+        self.request_repaint();
+    }
+    fn paint_frame<F: FnOnce(&mut skia_safe::Canvas)>(&self, f: F) -> Result<(), PaintError> {
+        match self {
+            Self::Skulpin(renderer) => {
+                let result = renderer.paint(f);
+                let failures = &self.frame_state().consecutive_failures;
+                match result {
+                    Ok(()) => failures.set(0),
+                    // The swapchain is rebuilt on resize, this isn't a failure of the device.
+                    Err(skulpin::ash::vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                        return Err(PaintError::SurfaceOutOfDate)
+                    }
+                    Err(_) => failures.set(failures.get() + 1),
+                }
+                result.map_err(PaintError::Skulpin)
+            }
+            Self::Gl(renderer) => renderer.paint(f),
+            Self::Raster(renderer) => {
+                renderer.paint(f);
+                Ok(())
+            }
+        }
+    }
+    /// Record `f`'s drawing into a picture that can be replayed with `paint_picture`, for caching
+    /// static scenes. Only drawing inside `bounds` is kept.
+    pub fn record<F: FnOnce(&mut skia_safe::Canvas)>(
+        &self,
+        bounds: skia_safe::Rect,
+        f: F,
+    ) -> Option<skia_safe::Picture> {
+        let mut recorder = skia_safe::PictureRecorder::new();
+        f(recorder.begin_recording(bounds, None));
+        recorder.finish_recording_as_picture(None)
+    }
+    pub fn paint_picture(&self, picture: &skia_safe::Picture) -> Result<(), PaintError> {
+        self.paint(|canvas| {
+            canvas.draw_picture(picture, None, None);
+        })
+    }
+    /// A handle other threads can use to queue drawing for the next `drain_and_paint`. All
+    /// handles feed the same queue.
+    pub fn render_queue(&self) -> RenderQueueSender {
+        let mut queue = self.frame_state().render_queue.borrow_mut();
+        let (sender, _) = queue.get_or_insert_with(mpsc::channel);
+        RenderQueueSender(sender.clone())
+    }
+    /// Paint one frame that runs every queued command in the order it was sent. Queueing doesn't
+    /// wake the event loop, so senders should also request a redraw, e.g. through an
+    /// `EventLoopProxy`. Commands stay queued while painting is skipped, e.g. when paused or
+    /// minimized.
+    pub fn drain_and_paint(&self) -> Result<(), PaintError> {
+        self.paint(|canvas| {
+            let commands: Vec<RenderCommand> = match &*self.frame_state().render_queue.borrow() {
+                Some((_, receiver)) => receiver.try_iter().collect(),
+                None => Vec::new(),
+            };
+            for command in commands {
+                command(canvas);
+            }
+        })
+    }
+    /// Clear the window to black and draw `image` over it, mapped to the surface by `fit`, e.g. to
+    /// show a decoded video frame. The image is placed in physical pixels regardless of the
+    /// coordinate system.
+    pub fn present_image(&self, image: &skia_safe::Image, fit: Fit) -> Result<(), PaintError> {
+        let size = self.inner_size();
+        let (width, height) = (size.width as f32, size.height as f32);
+        let (image_width, image_height) = (image.width() as f32, image.height() as f32);
+        let scale = match fit {
+            Fit::Stretch => None,
+            Fit::Contain => Some((width / image_width).min(height / image_height)),
+            Fit::Cover => Some((width / image_width).max(height / image_height)),
+            Fit::Center => Some(1.0),
+        };
+        let dst = match scale {
+            None => skia_safe::Rect::from_wh(width, height),
+            Some(scale) => {
+                let (dst_width, dst_height) = (image_width * scale, image_height * scale);
+                skia_safe::Rect::from_xywh(
+                    (width - dst_width) / 2.0,
+                    (height - dst_height) / 2.0,
+                    dst_width,
+                    dst_height,
+                )
+            }
+        };
+        self.paint_with_render_scale(false, |canvas| {
+            canvas.reset_matrix();
+            canvas.clear(skia_safe::Color::BLACK);
+            let mut paint = skia_safe::Paint::default();
+            paint.set_filter_quality(skia_safe::FilterQuality::Medium);
+            canvas.draw_image_rect(image, None, dst, &paint);
+        })
+    }
+    /// Like `paint`, but blocks until the GPU has finished the frame, so readbacks and work
+    /// shared with other APIs afterwards see it complete rather than in flight. This stalls the
+    /// CPU, so it isn't meant for every frame.
+    pub fn paint_and_fence<F: FnOnce(&mut skia_safe::Canvas)>(
+        &self,
+        f: F,
+    ) -> Result<(), PaintError> {
+        self.paint(f)?;
+        match self {
+            Self::Skulpin(renderer) => renderer.wait_idle().map_err(PaintError::Skulpin),
+            Self::Gl(renderer) => {
+                renderer.wait_idle();
+                Ok(())
+            }
+            Self::Raster(_) => Ok(()),
+        }
+    }
+    /// Like `paint`, but also measures how long the frame took. See `FrameTiming`.
+    pub fn paint_timed<F: FnOnce(&mut skia_safe::Canvas)>(
+        &self,
+        f: F,
+    ) -> Result<FrameTiming, PaintError> {
+        let mut cpu_paint = Duration::default();
+        let start = Instant::now();
+        self.paint(|canvas| {
+            let paint_start = Instant::now();
+            f(canvas);
+            cpu_paint = paint_start.elapsed();
+        })?;
+        Ok(FrameTiming {
+            cpu_paint,
+            present: start.elapsed() - cpu_paint,
+        })
+    }
+    /// Capture the last presented frame, or `None` if the active backend can't provide it
+    /// (Skulpin doesn't expose its swapchain surfaces). After `paint_no_present` this is the
+    /// frame it drew.
+    ///
+    /// The back buffer's contents are undefined once OpenGL has swapped it, so the OpenGL backend
+    /// copies every swapped frame on the GPU before presenting it, and this returns that copy.
+    /// The image may be GPU-backed; use `make_raster_image()` on it if CPU pixels are needed.
+    pub fn snapshot(&self) -> Option<skia_safe::Image> {
+        match self {
+            Self::Skulpin(_) => None,
+            Self::Gl(renderer) => Some(renderer.snapshot()),
+            Self::Raster(renderer) => Some(renderer.snapshot()),
+        }
+    }
+    /// Read back the pixels in `rect` of the frame `snapshot` returns, as tightly packed rows of
+    /// unpremultiplied RGBA8 bytes, so the row stride is `rect.width() * 4`. Returns `None` if
+    /// `rect` is empty or not within the surface, or if the active backend can't read back.
+    pub fn read_pixels(&self, rect: skia_safe::IRect) -> Option<Vec<u8>> {
+        match self {
+            Self::Skulpin(_) => None,
+            Self::Gl(renderer) => renderer.read_pixels(rect),
+            Self::Raster(renderer) => renderer.read_pixels(rect),
+        }
+    }
+    pub fn save_png(&self, path: &Path) -> Result<(), SaveError> {
+        let image = self.snapshot().ok_or(SaveError::SnapshotUnavailable)?;
+        save_image_png(&image, path)
+    }
+    /// Encode a `snapshot` as `format`, with `quality` from 0 to 100 for lossy formats such as
+    /// JPEG and WebP. Returns `None` where `snapshot` does, or if this Skia build can't encode
+    /// `format`.
+    pub fn encode_frame(
+        &self,
+        format: skia_safe::EncodedImageFormat,
+        quality: u32,
+    ) -> Option<skia_safe::Data> {
+        self.snapshot()?
+            .encode_to_data_with_quality(format, quality.min(100) as i32)
+    }
+    /// Draw a frame with `f` like `paint_no_present`, then redraw it through `filter`, e.g. a
+    /// blur from `skia_safe::image_filters::blur`, and present the result. This costs a snapshot
+    /// and a full-window filtered draw on top of the frame itself, and large blurs are
+    /// expensive. Like `paint_no_present`, this returns `PaintError::SurfaceUnavailable` on
+    /// Vulkan without calling `f`.
+    pub fn apply_image_filter<F: FnOnce(&mut skia_safe::Canvas)>(
+        &self,
+        filter: skia_safe::ImageFilter,
+        f: F,
+    ) -> Result<(), PaintError> {
+        self.paint_no_present(f)?;
+        let image = self.snapshot().ok_or(PaintError::SurfaceUnavailable)?;
+        let mut paint = skia_safe::Paint::default();
+        paint.set_image_filter(filter);
+        self.paint_with_render_scale(false, |canvas| {
+            canvas.reset_matrix();
+            canvas.clear(skia_safe::Color::TRANSPARENT);
+            canvas.draw_image(&image, (0.0, 0.0), Some(&paint));
+        })
+    }
+    /// A `snapshot` scaled down so its larger side is `max_dim` pixels, keeping the aspect ratio.
+    /// Returns `None` where `snapshot` does.
+    pub fn thumbnail(&self, max_dim: u32) -> Option<skia_safe::Image> {
+        let image = self.snapshot()?;
+        let scale = max_dim as f32 / image.width().max(image.height()) as f32;
+        let size = skia_safe::ISize::new(
+            ((image.width() as f32 * scale).round() as i32).max(1),
+            ((image.height() as f32 * scale).round() as i32).max(1),
+        );
+        let mut offscreen = self.create_offscreen_or_raster(size)?;
+        let mut paint = skia_safe::Paint::default();
+        paint.set_filter_quality(skia_safe::FilterQuality::Medium);
+        offscreen.paint(|canvas| {
+            canvas.draw_image_rect(
+                &image,
+                None,
+                skia_safe::Rect::from_wh(size.width as f32, size.height as f32),
+                &paint,
+            );
+        });
+        Some(offscreen.image())
+    }
+    /// Run `f` with the Skia GPU context, or return `None` on backends that don't expose it.
+    pub fn with_gpu_context<R>(
+        &self,
+        f: impl FnOnce(&mut skia_safe::gpu::Context) -> R,
+    ) -> Option<R> {
+        match self {
+            Self::Skulpin(_) | Self::Raster(_) => None,
+            Self::Gl(renderer) => Some(renderer.with_gpu_context(f)),
+        }
+    }
+    /// Rebuild the backend's surface at the current window size. Skulpin manages its swapchain
+    /// itself, so this does nothing there.
+    pub fn recreate_surface(&self) -> Result<(), RendererInitError> {
+        match self {
+            Self::Skulpin(_) => Ok(()),
+            Self::Gl(renderer) => renderer.recreate_surface(),
+            Self::Raster(renderer) => renderer.recreate_surface(),
+        }
+    }
+    /// The bytes and number of resources in Skia's GPU cache, or `None` on backends that don't
+    /// expose their GPU context.
+    pub fn gpu_resource_cache_usage(&self) -> Option<(usize, usize)> {
+        self.with_gpu_context(|context| {
+            let usage = context.resource_cache_usage();
+            (usage.resource_bytes, usage.resource_count)
+        })
+    }
+    /// Release cached GPU resources that nothing currently uses, e.g. under memory pressure.
+    /// They are recreated on demand, so the next frames may be slower. Does nothing on backends
+    /// that don't expose their GPU context.
+    pub fn purge_gpu_resources(&self) {
+        self.with_gpu_context(|context| context.purge_unlocked_resources(false));
+    }
+    /// Submit all drawing recorded so far to the GPU without presenting, including drawing into
+    /// offscreen surfaces. Work is submitted in the order it was recorded, and `paint` does the
+    /// same flush before presenting, so calling this first never reorders a frame.
+    ///
+    /// Skulpin only flushes inside its own draw call, and raster drawing happens immediately, so
+    /// this only does something on OpenGL.
+    pub fn flush(&self) {
+        if let Self::Gl(renderer) = self {
+            renderer.flush();
+        }
+    }
+    /// Request another repaint after every paint, for an uncapped render loop. This is meant to
+    /// be used with `ControlFlow::Poll`.
+    pub fn start_continuous_redraw(&self) {
+        self.frame_state().continuous_redraw.set(true);
+        self.request_repaint();
+    }
+    /// How many frames have been presented, by `paint`, `paint_surface`, `paint_region`,
+    /// `present` or a finished `PaintGuard`.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_state().frame_count.get()
+    }
+    /// Frames per second averaged over the last `FPS_WINDOW` presents, or `0.0` before there are
+    /// two of them. It's measured when presenting returns, so with vsync it tracks the display's
+    /// refresh rate, and it includes any idle time between frames.
+    pub fn average_fps(&self) -> f32 {
+        let recent_frames = self.frame_state().recent_frames.borrow();
+        match (recent_frames.front(), recent_frames.back()) {
+            (Some(first), Some(last)) if last > first => {
+                (recent_frames.len() - 1) as f32 / (*last - *first).as_secs_f32()
+            }
+            _ => 0.0,
+        }
+    }
+    /// Go back to only painting when a repaint is requested.
+    pub fn stop_continuous_redraw(&self) {
+        let frame_state = self.frame_state();
+        frame_state.continuous_redraw.set(false);
+        frame_state.next_frame.set(None);
+    }
+    /// Cap how often continuous redraw issues frames: instead of requesting the next frame right
+    /// after a paint, it waits until `1 / fps` has passed, which needs the event loop to call
+    /// `wait_for_next_frame`. `None` removes the cap. Paints requested by other means aren't
+    /// delayed, and the event loop keeps handling input while it waits.
+    pub fn set_frame_rate_limit(&self, fps: Option<u32>) {
+        let frame_state = self.frame_state();
+        frame_state.frame_rate_limit.set(fps);
+        // A frame waiting on the old limit is due now.
+        if fps.is_none() && frame_state.next_frame.take().is_some() {
+            self.request_repaint();
+        }
+    }
+    /// Why `Backend::Auto` didn't pick Vulkan: the debug output of the Vulkan construction
+    /// error, followed by the OpenGL one if raster was chosen. `None` when the first choice
+    /// succeeded or a backend was requested explicitly. On Vulkan, this instead says why the
+    /// `GpuPreference` couldn't be met, if it couldn't.
+    pub fn fallback_reason(&self) -> Option<&str> {
+        self.frame_state().fallback_reason.as_deref()
+    }
+    /// Whether Vulkan painting has failed often enough that `fall_back_to_gl` should be called.
+    /// Always `false` unless the builder enabled `auto_fallback_on_runtime_failure`.
+    pub fn needs_runtime_fallback(&self) -> bool {
+        let frame_state = self.frame_state();
+        frame_state.runtime_fallback.is_some()
+            && frame_state.consecutive_failures.get() >= RUNTIME_FALLBACK_THRESHOLD
+    }
+    /// Replace the Vulkan renderer with an OpenGL one in a new window of the same size and
+    /// title. The old window closes once the new one is open, so it may flicker during the
+    /// transition. Does nothing unless `needs_runtime_fallback` returns `true`.
+    pub fn fall_back_to_gl<E>(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<E>,
+    ) -> Result<(), RendererInitError> {
+        if !self.needs_runtime_fallback() {
+            return Ok(());
+        }
+        let mut builder = self.frame_state_mut().runtime_fallback.take().unwrap();
+        builder.window_builder = builder
+            .window_builder
+            .with_inner_size(self.window().inner_size())
+            .with_title(self.title());
+        let mut renderer = match builder.build_gl(event_loop) {
+            Ok(renderer) => renderer,
+            Err(e) => {
+                log::error!("Error during OpenGL fallback construction: {:?}", e);
+                self.frame_state_mut().runtime_fallback = Some(builder);
+                return Err(e);
+            }
+        };
+        if let Ok(position) = self.window().outer_position() {
+            renderer.window().set_outer_position(position);
+        }
+        let mut frame_state = std::mem::take(self.frame_state_mut());
+        log::warn!(
+            "Vulkan painting failed {} times in a row, Using OpenGL.",
+            frame_state.consecutive_failures.get()
+        );
+        frame_state.consecutive_failures.set(0);
+        frame_state.fallback_reason = Some(String::from("Vulkan painting failed repeatedly"));
+        *renderer.frame_state_mut() = frame_state;
+        *self = renderer;
+        Ok(())
+    }
+    fn frame_state(&self) -> &FrameState {
+        match self {
+            Self::Skulpin(renderer) => &renderer.frame_state,
+            Self::Gl(renderer) => &renderer.frame_state,
+            Self::Raster(renderer) => &renderer.frame_state,
+        }
+    }
+    fn frame_state_mut(&mut self) -> &mut FrameState {
+        match self {
+            Self::Skulpin(renderer) => &mut renderer.frame_state,
+            Self::Gl(renderer) => &mut renderer.frame_state,
+            Self::Raster(renderer) => &mut renderer.frame_state,
+        }
+    }
+    /// Allocate a GPU render target that's independent of the window, so it survives resizes.
+    /// Returns `None` on backends that don't expose a GPU context.
+    pub fn create_offscreen(&self, size: skia_safe::ISize) -> Option<OffscreenSurface> {
+        match self {
+            Self::Skulpin(_) | Self::Raster(_) => None,
+            Self::Gl(renderer) => renderer.create_offscreen(size),
+        }
+    }
+    /// `create_offscreen`, falling back to a raster surface where there's no GPU context.
+    pub(crate) fn create_offscreen_or_raster(
+        &self,
+        size: skia_safe::ISize,
+    ) -> Option<OffscreenSurface> {
+        self.create_offscreen(size)
+            .or_else(|| skia_safe::Surface::new_raster_n32_premul(size).map(OffscreenSurface::new))
+    }
+    pub fn request_repaint(&self) {
+        let frame_state = self.frame_state();
+        if frame_state.coalesce_repaints.get() && frame_state.repaint_pending.replace(true) {
+            return;
+        }
+        match self {
+            Self::Skulpin(renderer) => renderer.request_repaint(),
+            Self::Gl(renderer) => renderer.request_repaint(),
+            Self::Raster(renderer) => renderer.request_repaint(),
+        }
+    }
+    /// Scale every paint by `scale` on top of the coordinate system's scale factor, e.g. for a
+    /// user-controlled zoom, so with `CoordinateSystem::Logical` one canvas unit is
+    /// `scale_factor() * scale` physical pixels. Positions from `physical_to_logical` have to be
+    /// divided by it to hit-test against what's drawn, or physical ones by `canvas_scale`.
+    pub fn set_content_scale(&self, scale: f32) {
+        self.frame_state().content_scale.set(Some(scale));
+        self.request_repaint();
+    }
+    pub fn content_scale(&self) -> f32 {
+        self.frame_state().content_scale.get().unwrap_or(1.0)
+    }
+    /// How many physical pixels one canvas unit covers in paint closures: the coordinate
+    /// system's scale factor times the content scale.
+    pub fn canvas_scale(&self) -> f32 {
+        canvas_scale(
+            self.coordinate_system(),
+            self.scale_factor(),
+            self.content_scale(),
+        )
+    }
+    pub fn scale_factor(&self) -> f64 {
+        match self {
+            Self::Skulpin(renderer) => renderer.scale_factor(),
+            Self::Gl(renderer) => renderer.scale_factor(),
+            Self::Raster(renderer) => renderer.scale_factor(),
+        }
+    }
+    /// The window's drawable size. Every backend sizes its render target in physical pixels, so
+    /// this is also the surface size.
+    pub fn inner_size(&self) -> PhysicalSize<u32> {
+        self.window().inner_size()
+    }
+    pub fn logical_size(&self) -> LogicalSize<u32> {
+        self.inner_size().to_logical(self.scale_factor())
+    }
+    /// Convert a position from winit, such as `CursorMoved`'s, into the units the canvas draws
+    /// in with `CoordinateSystem::Logical`, for hit-testing.
+    pub fn physical_to_logical(&self, position: PhysicalPosition<f64>) -> LogicalPosition<f64> {
+        position.to_logical(self.scale_factor())
+    }
+    pub fn logical_to_physical(&self, position: LogicalPosition<f64>) -> PhysicalPosition<f64> {
+        position.to_physical(self.scale_factor())
+    }
+    /// The backend that is actually in use. This never returns `Backend::Auto` and only changes
+    /// through `fall_back_to_gl`.
+    pub fn backend(&self) -> Backend {
+        match self {
+            Self::Skulpin(_) => Backend::Vulkan,
+            Self::Gl(_) => Backend::OpenGl,
+            Self::Raster(_) => Backend::Raster,
+        }
+    }
+    pub fn config_snapshot(&self) -> RendererConfigSnapshot {
+        let (stencil_bits, color_type, present_mode) = match self {
+            Self::Skulpin(renderer) => (None, None, Some(renderer.config.present_mode)),
+            Self::Gl(renderer) => (
+                Some(renderer.stencil_bits()),
+                Some(renderer.config.color_type),
+                Some(renderer.config.present_mode),
+            ),
+            Self::Raster(_) => (None, Some(skia_safe::ColorType::BGRA8888), None),
+        };
+        RendererConfigSnapshot {
+            backend: self.backend(),
+            scale_factor: self.scale_factor(),
+            inner_size: self.inner_size(),
+            msaa_samples: self.msaa_samples(),
+            stencil_bits,
+            color_type,
+            present_mode,
+        }
+    }
+    /// Describe the GPU behind the active backend, for diagnostics. The strings come straight
+    /// from the driver and have no stable format.
+    pub fn adapter_info(&self) -> Option<AdapterInfo> {
+        match self {
+            Self::Skulpin(renderer) => Some(renderer.adapter_info()),
+            Self::Gl(renderer) => Some(renderer.adapter_info()),
+            Self::Raster(_) => None,
+        }
+    }
+    /// The number of MSAA samples the surface actually got, or `None` if it isn't multisampled.
+    /// Only OpenGL negotiates multisampling; the other backends always return `None`.
+    pub fn msaa_samples(&self) -> Option<u32> {
+        match self {
+            Self::Skulpin(_) | Self::Raster(_) => None,
+            Self::Gl(renderer) => renderer.msaa_samples(),
+        }
+    }
+    /// Whether the OpenGL context is current on this thread, or `None` on other backends.
+    pub fn is_current(&self) -> Option<bool> {
+        match self {
+            Self::Skulpin(_) | Self::Raster(_) => None,
+            Self::Gl(renderer) => Some(renderer.is_current()),
+        }
+    }
+    /// Whether OpenGL granted a double-buffered pixel format, or `None` on other backends.
+    pub fn is_double_buffered(&self) -> Option<bool> {
+        match self {
+            Self::Skulpin(_) | Self::Raster(_) => None,
+            Self::Gl(renderer) => Some(renderer.is_double_buffered()),
+        }
+    }
+    /// What the OpenGL context supports, or `None` on other backends. See
+    /// `GlRenderer::extensions` for the full extension list.
+    pub fn gl_capabilities(&self) -> Option<GlCapabilities> {
+        match self {
+            Self::Skulpin(_) | Self::Raster(_) => None,
+            Self::Gl(renderer) => Some(renderer.capabilities()),
+        }
+    }
+    pub fn is_gpu_accelerated(&self) -> bool {
+        match self {
+            Self::Skulpin(_) | Self::Gl(_) => true,
+            Self::Raster(_) => false,
+        }
+    }
+    /// Every backend reads the scale factor from the window at the start of each paint, so this
+    /// only requests a redraw. Call it along with `resize` when handling
+    /// `WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size }`.
+    pub fn set_scale_factor(&self, scale_factor: f64) {
+        match self {
+            Self::Skulpin(renderer) => renderer.set_scale_factor(scale_factor),
+            Self::Gl(renderer) => renderer.set_scale_factor(scale_factor),
+            Self::Raster(renderer) => renderer.set_scale_factor(scale_factor),
+        }
+    }
+    pub fn available_monitors(&self) -> impl Iterator<Item = winit::monitor::MonitorHandle> {
+        self.window().available_monitors()
+    }
+    pub fn current_monitor(&self) -> winit::monitor::MonitorHandle {
+        self.window().current_monitor()
+    }
+    /// Move the window, e.g. to `monitor.position()`. Moving onto a monitor with a different
+    /// scale factor makes winit send `ScaleFactorChanged`, which `handle_window_event` applies.
+    pub fn set_outer_position(&self, position: impl Into<winit::dpi::Position>) {
+        self.window().set_outer_position(position);
+    }
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.window().set_cursor_visible(visible);
+    }
+    /// Confine the cursor to the window. winit 0.22 only has an on/off grab, whose exact
+    /// behavior (confined or locked in place) depends on the platform.
+    pub fn set_cursor_grab(&self, grab: bool) -> Result<(), winit::error::ExternalError> {
+        self.window().set_cursor_grab(grab)
+    }
+    pub fn set_cursor_position(
+        &self,
+        position: impl Into<winit::dpi::Position>,
+    ) -> Result<(), winit::error::ExternalError> {
+        self.window().set_cursor_position(position)
+    }
+    /// Keep the surface in sync with this renderer's window: `Resized` calls `resize`, and
+    /// `ScaleFactorChanged` calls `set_scale_factor` and `resize`. Returns whether the event
+    /// needs a redraw, which these calls have already requested. Only pass events whose
+    /// `window_id` matches `window().id()`.
+    pub fn handle_window_event(&self, event: &winit::event::WindowEvent) -> bool {
+        match event {
+            winit::event::WindowEvent::Resized(size) => {
+                self.resize(*size);
+                true
+            }
+            winit::event::WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+            } => {
+                self.set_scale_factor(*scale_factor);
+                self.resize(**new_inner_size);
+                true
+            }
+            _ => false,
+        }
+    }
+    /// Enter or leave fullscreen and resize the surface to match. Some platforms only apply the
+    /// new size later, and report it with a `Resized` event that should also be passed on.
+    pub fn set_fullscreen(&self, fullscreen: Option<winit::window::Fullscreen>) {
+        self.window().set_fullscreen(fullscreen);
+        self.resize(self.window().inner_size());
+    }
+    /// Show a window built with `WindowRendererBuilder::visible(false)`.
+    pub fn show(&self) {
+        self.window().set_visible(true);
+    }
+    pub fn set_title(&self, title: &str) {
+        self.window().set_title(title);
+        *self.frame_state().title.borrow_mut() = title.to_owned();
+    }
+    /// The title given to the builder or the last `set_title`.
+    pub fn title(&self) -> String {
+        self.frame_state().title.borrow().clone()
+    }
+    pub fn window(&self) -> &winit::window::Window {
+        match self {
+            Self::Skulpin(renderer) => renderer.window(),
+            Self::Gl(renderer) => renderer.window(),
+            Self::Raster(renderer) => renderer.window(),
+        }
+    }
+    pub fn window_id(&self) -> winit::window::WindowId {
+        self.window().id()
+    }
+    /// Tear the renderer down explicitly, consuming it, instead of relying on drop order, e.g.
+    /// from `Event::LoopDestroyed`. On OpenGL this frees Skia's GPU resources and abandons its
+    /// context while the GL context is still current, which avoids crashes on exit with some
+    /// drivers; see `GlRenderer::shutdown`. On Vulkan it waits for the device to go idle before
+    /// Skulpin tears down.
+    pub fn shutdown(self) {
+        match self {
+            Self::Skulpin(renderer) => {
+                if let Err(e) = renderer.wait_idle() {
+                    log::warn!(
+                        "Error waiting for the Vulkan device before shutdown: {:?}",
+                        e
+                    );
+                }
+            }
+            Self::Gl(renderer) => renderer.shutdown(),
+            Self::Raster(_) => {}
+        }
+    }
+}
+/// Delegates to the winit window. raw-window-handle 0.3 carries the display connection inside the
+/// window handle, so there is no separate display handle to implement.
+unsafe impl raw_window_handle::HasRawWindowHandle for WindowRenderer {
+    fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
+        raw_window_handle::HasRawWindowHandle::raw_window_handle(self.window())
+    }
+}
+
+/// A surface that's independent of the window, from `WindowRenderer::create_offscreen`. GPU
+/// surfaces make their renderer's OpenGL context current before they're drawn or read; once the
+/// renderer is gone they can't be used.
+pub struct OffscreenSurface {
+    surface: skia_safe::Surface,
+    /// The GL context a GPU surface was created on, `None` for raster surfaces.
+    context: Option<Weak<GlContextSlot>>,
+}
+impl OffscreenSurface {
+    fn new(surface: skia_safe::Surface) -> Self {
+        Self {
+            surface,
+            context: None,
+        }
+    }
+    fn with_context(surface: skia_safe::Surface, context: Weak<GlContextSlot>) -> Self {
+        Self {
+            surface,
+            context: Some(context),
+        }
+    }
+    fn bind(&self) {
+        if let Some(context) = self.context.as_ref().and_then(Weak::upgrade) {
+            if let Err(e) = make_context_current(&context) {
+                log::warn!("Error making the OpenGL context current: {:?}", e);
+            }
+        }
+    }
+    pub fn paint<F: FnOnce(&mut skia_safe::Canvas)>(&mut self, f: F) {
+        self.bind();
+        let canvas = self.surface.canvas();
+        f(canvas);
+        canvas.flush();
+    }
+    pub fn image(&mut self) -> skia_safe::Image {
+        self.bind();
+        self.surface.image_snapshot()
+    }
+    /// Draw the current contents with their top-left corner at `position`, eg. into the canvas
+    /// of a window paint closure.
+    pub fn draw_into(
+        &mut self,
+        canvas: &mut skia_safe::Canvas,
+        position: impl Into<skia_safe::Point>,
+        paint: Option<&skia_safe::Paint>,
+    ) {
+        canvas.draw_image(&self.image(), position, paint);
+    }
+    pub fn size(&self) -> skia_safe::ISize {
+        skia_safe::ISize::new(self.surface.width(), self.surface.height())
+    }
+    /// Direct access for drawing the `paint` methods don't cover. The context is made current
+    /// first, but drawing through the surface doesn't keep it current.
+    pub fn surface(&mut self) -> &mut skia_safe::Surface {
+        self.bind();
+        &mut self.surface
+    }
+}
+
+/// Hooks for the phases of each frame, registered with `WindowRenderer::set_frame_observer`.
+/// Every frame calls `on_frame_begin` before drawing and `on_paint_end` once the closure has
+/// returned, or once the frame failed without calling it, so the two always come in pairs.
+/// Frames that present then call `on_present_end` once the swap or copy to the window has
+/// returned, whether or not it succeeded; `paint_no_present` frames end without it, and
+/// `present` frames call `on_paint_end` straight after `on_frame_begin`. Skipped paints call
+/// nothing. The methods do nothing by default.
+pub trait FrameObserver {
+    fn on_frame_begin(&self) {}
+    fn on_paint_end(&self) {}
+    fn on_present_end(&self) {}
+}
+
+/// A frame in progress, from `WindowRenderer::begin_paint`.
+pub struct PaintGuard<'a> {
+    renderer: &'a WindowRenderer,
+    /// `None` once the frame has been presented.
+    surface: Option<RefMut<'a, skia_safe::Surface>>,
+    save_count: usize,
+    _painting: PaintingGuard<'a>,
+}
+impl PaintGuard<'_> {
+    pub fn canvas(&mut self) -> &mut skia_safe::Canvas {
+        self.surface().canvas()
+    }
+    pub fn surface(&mut self) -> &mut skia_safe::Surface {
+        self.surface.as_mut().unwrap()
+    }
+    /// Present the frame, returning the errors `paint` would.
+    pub fn finish(mut self) -> Result<(), PaintError> {
+        self.present()
+    }
+    fn present(&mut self) -> Result<(), PaintError> {
+        let mut surface = match self.surface.take() {
+            Some(surface) => surface,
+            None => return Ok(()),
+        };
+        self.renderer.end_drawing(surface.canvas(), self.save_count);
+        drop(surface);
+        if self.renderer.skips_paint() {
+            return Ok(());
+        }
+        // The drawing is already on the surface, so the backends present it with an empty frame.
+        let result = match self.renderer {
+            WindowRenderer::Skulpin(_) => unreachable!(),
+            WindowRenderer::Gl(renderer) => renderer.paint_surface(|_| {}),
+            WindowRenderer::Raster(renderer) => {
+                renderer.paint_surface(|_| {});
+                Ok(())
+            }
+        };
+        self.renderer.finish_frame(&result);
+        result
+    }
+}
+impl Drop for PaintGuard<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = self.present() {
+            log::warn!("Error presenting a frame from begin_paint: {}", e);
+        }
+    }
+}
+
+type RenderCommand = Box<dyn FnOnce(&mut skia_safe::Canvas) + Send>;
+
+/// Queues drawing from any thread for `WindowRenderer::drain_and_paint`. See
+/// `WindowRenderer::render_queue`.
+#[derive(Clone)]
+pub struct RenderQueueSender(mpsc::Sender<RenderCommand>);
+impl RenderQueueSender {
+    /// Returns `false` if the renderer has been dropped.
+    pub fn push(&self, command: impl FnOnce(&mut skia_safe::Canvas) + Send + 'static) -> bool {
+        self.0.send(Box::new(command)).is_ok()
+    }
+}
+
+/// The OpenGL limits and extensions this crate's features depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlCapabilities {
+    /// Whether `paint_region` can present only the damaged region.
+    pub swap_buffers_with_damage: bool,
+    /// The largest width or height a surface or texture can have.
+    pub max_texture_size: u32,
+    /// The most MSAA samples a framebuffer can have, for `GlConfig::msaa_samples`.
+    pub max_samples: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdapterInfo {
+    pub name: String,
+    pub backend: Backend,
+    pub driver: Option<String>,
+}
+
+/// How `WindowRenderer::present_image` maps an image onto the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fit {
+    /// Fill the window exactly, distorting the aspect ratio if needed.
+    Stretch,
+    /// Scale to fit inside the window, keeping the aspect ratio and leaving bars.
+    Contain,
+    /// Scale to cover the whole window, keeping the aspect ratio and cropping the overflow.
+    Cover,
+    /// Draw at its own size in the middle of the window.
+    Center,
+}
+
+/// The live configuration of a renderer, for logging and bug reports. See
+/// `WindowRenderer::config_snapshot`. Fields are `None` where the backend doesn't have or
+/// expose the setting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RendererConfigSnapshot {
+    pub backend: Backend,
+    pub scale_factor: f64,
+    pub inner_size: PhysicalSize<u32>,
+    pub msaa_samples: Option<u32>,
+    pub stencil_bits: Option<u8>,
+    pub color_type: Option<skia_safe::ColorType>,
+    pub present_mode: Option<PresentMode>,
+}
+
+/// Wall-clock times measured on the CPU with `Instant`, not GPU execution times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTiming {
+    /// Time spent in the paint closure.
+    pub cpu_paint: Duration,
+    /// Everything else in the frame: flushing, waiting for a swapchain image and presenting. The
+    /// frame rate limit doesn't block paints, so it's never included.
+    pub present: Duration,
+}
+
+#[derive(Debug)]
+pub enum PaintError {
+    Skulpin(skulpin::ash::vk::Result),
+    Gl(glutin::ContextError),
+    /// The OpenGL context was lost and rebuilding the Skia state on it failed. The renderer
+    /// should be recreated.
+    GlContextLost,
+    /// The OpenGL context was lost while presenting and the Skia state has been rebuilt on it.
+    /// The frame was dropped and a repaint requested, so painting again draws it.
+    GlContextRecovered,
+    /// The active backend doesn't expose its `skia_safe::Surface`.
+    SurfaceUnavailable,
+    /// The window's size no longer matches the surface, e.g. because the compositor changed it
+    /// without a `Resized` event. Vulkan reports `ERROR_OUT_OF_DATE_KHR`; OpenGL checks the
+    /// sizes before drawing and doesn't call the closure. Call `resize` with `inner_size` and
+    /// paint again.
+    SurfaceOutOfDate,
+}
+impl PaintError {
+    /// Whether the GPU context or device was lost. OpenGL rebuilds its Skia state on a lost
+    /// context by itself, reporting `GlContextRecovered` when that worked and `GlContextLost`
+    /// when it failed.
+    pub fn is_context_lost(&self) -> bool {
+        match self {
+            Self::Skulpin(e) => *e == skulpin::ash::vk::Result::ERROR_DEVICE_LOST,
+            Self::Gl(e) => matches!(e, glutin::ContextError::ContextLost),
+            Self::GlContextLost | Self::GlContextRecovered => true,
+            Self::SurfaceUnavailable | Self::SurfaceOutOfDate => false,
+        }
+    }
+    /// Whether painting again may succeed without recreating the renderer. When this is `false`
+    /// the renderer should be rebuilt, or on Vulkan replaced with `fall_back_to_gl`.
+    pub fn is_recoverable(&self) -> bool {
+        use skulpin::ash::vk;
+
+        match self {
+            Self::Skulpin(e) => matches!(
+                *e,
+                vk::Result::ERROR_OUT_OF_DATE_KHR
+                    | vk::Result::SUBOPTIMAL_KHR
+                    | vk::Result::TIMEOUT
+                    | vk::Result::NOT_READY
+            ),
+            Self::Gl(e) => !matches!(e, glutin::ContextError::FunctionUnavailable),
+            Self::GlContextLost | Self::SurfaceUnavailable => false,
+            Self::GlContextRecovered | Self::SurfaceOutOfDate => true,
+        }
+    }
 }
-
-impl WindowRenderer {
-    pub fn new<E>(
-        window_builder: winit::window::WindowBuilder,
-        event_loop: &EventLoopWindowTarget<E>,
-    ) -> Self {
-        SkulpinRenderer::new(window_builder.clone(), event_loop)
-            .map(Self::Skulpin)
-            .unwrap_or_else(|e| {
-                eprintln!(
-                    "Error during skulpin renderer construction: {:?}, Using OpenGL.",
-                    e
-                );
-                Self::Gl(GlRenderer::new(window_builder, event_loop))
-            })
+impl fmt::Display for PaintError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Skulpin(e) => write!(f, "Vulkan present failed: {}", e),
+            Self::Gl(e) => write!(f, "OpenGL context error: {}", e),
+            Self::GlContextLost => write!(f, "OpenGL context was lost and could not be recovered"),
+            Self::GlContextRecovered => write!(f, "OpenGL context was lost and the frame dropped"),
+            Self::SurfaceUnavailable => write!(f, "The backend doesn't expose its surface"),
+            Self::SurfaceOutOfDate => write!(f, "The surface no longer matches the window size"),
+        }
     }
-    pub fn resize(&self, size: PhysicalSize<u32>) {
+}
+impl std::error::Error for PaintError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Self::Skulpin(_) => {}
-            Self::Gl(renderer) => renderer.resize(size),
+            Self::Skulpin(e) => Some(e),
+            Self::Gl(e) => Some(e),
+            Self::GlContextLost
+            | Self::GlContextRecovered
+            | Self::SurfaceUnavailable
+            | Self::SurfaceOutOfDate => None,
         }
     }
-    pub fn paint<F: FnOnce(&mut skia_safe::Canvas)>(&self, f: F) -> Result<(), PaintError> {
+}
+
+#[derive(Debug)]
+pub enum ResizeError {
+    /// The driver can't render to a surface with a side longer than `max` pixels.
+    TooLarge {
+        requested: PhysicalSize<u32>,
+        max: u32,
+    },
+    Surface(RendererInitError),
+}
+impl fmt::Display for ResizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::Skulpin(renderer) => renderer.paint(f).map_err(PaintError::Skulpin),
-            Self::Gl(renderer) => renderer.paint(f).map_err(PaintError::Gl),
+            Self::TooLarge { requested, max } => write!(
+                f,
+                "{}x{} is larger than the maximum surface size of {}",
+                requested.width, requested.height, max
+            ),
+            Self::Surface(e) => write!(f, "Error recreating the surface: {}", e),
         }
     }
-    pub fn request_repaint(&self) {
+}
+impl std::error::Error for ResizeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Self::Skulpin(renderer) => renderer.request_repaint(),
-            Self::Gl(renderer) => renderer.request_repaint(),
+            Self::TooLarge { .. } => None,
+            Self::Surface(e) => Some(e),
         }
     }
-    pub fn scale_factor(&self) -> f64 {
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    SnapshotUnavailable,
+    Encode,
+    Io(io::Error),
+}
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::Skulpin(renderer) => renderer.scale_factor(),
-            Self::Gl(renderer) => renderer.scale_factor(),
+            Self::SnapshotUnavailable => write!(f, "The backend can't snapshot its surface"),
+            Self::Encode => write!(f, "Error encoding the image"),
+            Self::Io(e) => write!(f, "Error writing the image: {}", e),
         }
     }
-    pub fn window(&self) -> &winit::window::Window {
+}
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Self::Skulpin(renderer) => &renderer.winit_window,
-            Self::Gl(renderer) => renderer.windowed_context.window(),
+            Self::SnapshotUnavailable | Self::Encode => None,
+            Self::Io(e) => Some(e),
         }
     }
 }
 
 #[derive(Debug)]
-pub enum PaintError {
-    Skulpin(skulpin::ash::vk::Result),
-    Gl(glutin::ContextError),
+pub enum RendererInitError {
+    Window(winit::error::OsError),
+    Vulkan(CreateRendererError),
+    GlCreation(glutin::CreationError),
+    GlContext(glutin::ContextError),
+    SkiaContext,
+    SkiaSurface,
+    /// softbuffer couldn't attach to the window. `None` when the window system isn't one the
+    /// raster backend supports.
+    Raster(Option<Box<dyn std::error::Error + Send + Sync>>),
+    Icon(winit::window::BadIcon),
+    /// `GlConfig::color_type` has no matching framebuffer format, or the driver's pixel format
+    /// can't hold it.
+    UnsupportedColorType(skia_safe::ColorType),
+    /// The window was created with a side longer than the backend can present, `max` pixels.
+    TooLarge {
+        requested: PhysicalSize<u32>,
+        max: u32,
+    },
+}
+impl RendererInitError {
+    /// Whether this came from setting up OpenGL itself, as opposed to creating the window, so
+    /// that another backend is worth trying.
+    fn allows_fallback(&self) -> bool {
+        match self {
+            Self::GlCreation(glutin::CreationError::Window(_)) => false,
+            Self::GlCreation(_) | Self::GlContext(_) | Self::SkiaContext | Self::SkiaSurface => {
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for RendererInitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Window(e) => write!(f, "Error creating the window: {}", e),
+            Self::Vulkan(e) => write!(f, "Error creating the Vulkan renderer: {}", e),
+            Self::GlCreation(e) => write!(f, "Error creating the OpenGL context: {}", e),
+            Self::GlContext(e) => write!(f, "OpenGL context error: {}", e),
+            Self::SkiaContext => write!(f, "Error creating the Skia GPU context"),
+            Self::SkiaSurface => write!(f, "Error creating the Skia surface"),
+            Self::Raster(Some(e)) => write!(f, "Error creating the raster renderer: {}", e),
+            Self::Raster(None) => write!(f, "The raster backend doesn't support this window"),
+            Self::Icon(e) => write!(f, "Invalid window icon: {}", e),
+            Self::UnsupportedColorType(color_type) => {
+                write!(f, "Unsupported color type {:?}", color_type)
+            }
+            Self::TooLarge { requested, max } => write!(
+                f,
+                "{}x{} is larger than the maximum surface size of {}",
+                requested.width, requested.height, max
+            ),
+        }
+    }
+}
+impl std::error::Error for RendererInitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Window(e) => Some(e),
+            Self::Vulkan(e) => Some(e),
+            Self::GlCreation(e) => Some(e),
+            Self::GlContext(e) => Some(e),
+            Self::Raster(e) => e.as_deref().map(|e| e as _),
+            Self::Icon(e) => Some(e),
+            Self::SkiaContext
+            | Self::SkiaSurface
+            | Self::UnsupportedColorType(_)
+            | Self::TooLarge { .. } => None,
+        }
+    }
+}
+
+/// How many consecutive Vulkan paint failures make `needs_runtime_fallback` return `true`.
+pub const RUNTIME_FALLBACK_THRESHOLD: u32 = 3;
+
+/// How many recent presents `WindowRenderer::average_fps` averages over.
+pub const FPS_WINDOW: usize = 60;
+
+/// Per-window state that `WindowRenderer` keeps regardless of backend.
+#[derive(Default)]
+struct FrameState {
+    continuous_redraw: Cell<bool>,
+    fallback_reason: Option<String>,
+    clear_color: Option<skia_safe::Color>,
+    /// winit can set the title but not read it back, so the last one set is cached here.
+    title: RefCell<String>,
+    consecutive_failures: Cell<u32>,
+    /// The builder to rebuild with on OpenGL, if runtime fallback is enabled.
+    runtime_fallback: Option<Box<WindowRendererBuilder>>,
+    painting: Cell<bool>,
+    /// Whether `on_frame_begin` has been sent without its `on_paint_end`.
+    frame_open: Cell<bool>,
+    frame_rate_limit: Cell<Option<u32>>,
+    /// When continuous redraw under a frame rate limit should request the next frame.
+    next_frame: Cell<Option<Instant>>,
+    render_queue: RefCell<Option<(mpsc::Sender<RenderCommand>, mpsc::Receiver<RenderCommand>)>>,
+    frame_count: Cell<u64>,
+    recent_frames: RefCell<VecDeque<Instant>>,
+    paused: Cell<bool>,
+    /// `None` until `set_content_scale` is called, which means `1.0`.
+    content_scale: Cell<Option<f32>>,
+    coalesce_repaints: Cell<bool>,
+    /// Whether `request_repaint` has been called since the last paint started.
+    repaint_pending: Cell<bool>,
+    frame_observer: RefCell<Option<Box<dyn FrameObserver>>>,
+    /// `None` until `set_render_scale` is called, which means `1.0`.
+    render_scale: Cell<Option<f32>>,
+    scaled_target: RefCell<Option<OffscreenSurface>>,
+    /// On OpenGL, a copy of the last swapped frame, taken before the swap left the back buffer
+    /// undefined. `None` when the surface itself holds the latest frame.
+    last_frame: RefCell<Option<skia_safe::Image>>,
+}
+
+/// Marks a paint as in progress, clearing the mark even if the paint closure panics.
+struct PaintingGuard<'a>(&'a Cell<bool>);
+impl<'a> PaintingGuard<'a> {
+    fn new(painting: &'a Cell<bool>) -> Self {
+        painting.set(true);
+        Self(painting)
+    }
+}
+impl Drop for PaintingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
 }
 
 pub struct SkulpinRenderer {
     winit_window: winit::window::Window,
     renderer: RefCell<skulpin::Renderer>,
+    config: VulkanConfig,
+    frame_state: FrameState,
 }
 impl SkulpinRenderer {
     pub fn new<E>(
         window_builder: winit::window::WindowBuilder,
         event_loop: &EventLoopWindowTarget<E>,
-    ) -> Result<Self, CreateRendererError> {
+    ) -> Result<Self, RendererInitError> {
+        Self::with_config(window_builder, event_loop, VulkanConfig::default())
+    }
+    pub fn with_config<E>(
+        window_builder: winit::window::WindowBuilder,
+        event_loop: &EventLoopWindowTarget<E>,
+        config: VulkanConfig,
+    ) -> Result<Self, RendererInitError> {
+        let requested_size = window_builder.window.inner_size;
         let winit_window = window_builder
             .build(&event_loop)
-            .expect("Failed to create window");
+            .map_err(RendererInitError::Window)?;
+        log_size_mismatch(requested_size, &winit_window);
         let skulpin_window = skulpin::WinitWindow::new(&winit_window);
-        let renderer = skulpin::RendererBuilder::new()
-            .use_vulkan_debug_layer(true)
-            .coordinate_system(CoordinateSystem::Logical)
-            .build(&skulpin_window)?;
+        let build = |validation| {
+            let builder = skulpin::RendererBuilder::new()
+                .use_vulkan_debug_layer(validation)
+                .coordinate_system(config.coordinate_system.into())
+                .present_mode_priority(config.present_mode.skulpin_priority());
+            match config.gpu_preference {
+                Some(GpuPreference::HighPerformance) => builder.prefer_discrete_gpu(),
+                Some(GpuPreference::LowPower) => builder.prefer_integrated_gpu(),
+                _ => builder,
+            }
+            .build(&skulpin_window)
+        };
+        let renderer = match build(config.validation) {
+            Err(CreateRendererError::CreateInstanceError(e)) if config.validation => {
+                log::warn!(
+                    "Error creating Vulkan instance with validation layers: {:?}, \
+                     Retrying without them.",
+                    e
+                );
+                build(false)
+            }
+            result => result,
+        }
+        .map_err(RendererInitError::Vulkan)?;
 
-        Ok(Self {
+        let mut renderer = Self {
             winit_window,
             renderer: RefCell::new(renderer),
-        })
+            config,
+            frame_state: FrameState::default(),
+        };
+        if let Some(reason) = renderer.unmet_gpu_preference() {
+            log::warn!("{}", reason);
+            renderer.frame_state.fallback_reason = Some(reason);
+        }
+        Ok(renderer)
     }
     pub fn paint<F: FnOnce(&mut skia_safe::Canvas)>(
         &self,
@@ -99,122 +2214,880 @@ impl SkulpinRenderer {
             .borrow_mut()
             .draw(&window, |canvas, _coordinate_system_helper| f(canvas))
     }
+    /// Block until the device has finished all submitted work.
+    pub fn wait_idle(&self) -> Result<(), skulpin::ash::vk::Result> {
+        use skulpin::ash::version::DeviceV1_0;
+
+        unsafe {
+            self.renderer
+                .borrow()
+                .vulkan_logical_device()
+                .device_wait_idle()
+        }
+    }
+    /// Skulpin rebuilds its swapchain on the next draw once it sees the window's physical size
+    /// change, so all that's needed here is to make sure that draw happens.
+    pub fn resize(&self, _size: PhysicalSize<u32>) {
+        self.winit_window.request_redraw()
+    }
     pub fn request_repaint(&self) {
         self.winit_window.request_redraw()
     }
     pub fn scale_factor(&self) -> f64 {
         self.winit_window.scale_factor()
     }
+    fn physical_device_properties(&self) -> skulpin::ash::vk::PhysicalDeviceProperties {
+        use skulpin::ash::version::InstanceV1_0;
+
+        let renderer = self.renderer.borrow();
+        unsafe {
+            renderer
+                .vulkan_instance()
+                .get_physical_device_properties(renderer.vulkan_physical_device())
+        }
+    }
+    fn unmet_gpu_preference(&self) -> Option<String> {
+        use skulpin::ash::vk::PhysicalDeviceType;
+
+        let preference = self.config.gpu_preference.as_ref()?;
+        let device_type = self.physical_device_properties().device_type;
+        let name = self.adapter_info().name;
+        let met = match preference {
+            GpuPreference::HighPerformance => device_type == PhysicalDeviceType::DISCRETE_GPU,
+            GpuPreference::LowPower => device_type == PhysicalDeviceType::INTEGRATED_GPU,
+            GpuPreference::ByName(wanted) => name.to_lowercase().contains(&wanted.to_lowercase()),
+        };
+        if met {
+            None
+        } else {
+            Some(format!(
+                "GPU preference {:?} couldn't be met, using {}",
+                preference, name
+            ))
+        }
+    }
+    pub fn adapter_info(&self) -> AdapterInfo {
+        let properties = self.physical_device_properties();
+        let name = unsafe { CStr::from_ptr(properties.device_name.as_ptr()) };
+        AdapterInfo {
+            name: name.to_string_lossy().into_owned(),
+            backend: Backend::Vulkan,
+            driver: Some(format!("{:#x}", properties.driver_version)),
+        }
+    }
+    /// Skulpin reads the scale factor from the window on every draw, so there's nothing to do.
+    pub fn set_scale_factor(&self, _scale_factor: f64) {
+        self.winit_window.request_redraw()
+    }
+    pub fn window(&self) -> &winit::window::Window {
+        &self.winit_window
+    }
+}
+
+/// How `GlRenderer::present` finishes a frame.
+enum Swap {
+    Full,
+    Damage(skia_safe::IRect),
+    Skip,
 }
 
 pub struct GlRenderer {
-    windowed_context: glutin::WindowedContext<glutin::PossiblyCurrent>,
+    /// Fields are dropped in order: Skia's offscreen surfaces in the frame state, the window
+    /// surface and the Skia context go before the GL context they use, and the window last.
+    frame_state: FrameState,
+    surface: RefCell<skia_safe::Surface>,
+    backend_render_target: RefCell<skia_safe::gpu::BackendRenderTarget>,
     gr_context: RefCell<skia_safe::gpu::Context>,
+    config: GlConfig,
     fb_info: skia_safe::gpu::gl::FramebufferInfo,
-    backend_render_target: RefCell<skia_safe::gpu::BackendRenderTarget>,
-    surface: RefCell<skia_safe::Surface>,
+    /// `GL_MAX_TEXTURE_SIZE`, the largest width or height a surface can have.
+    max_surface_size: u32,
+    /// Split from its window so it can be made current again through `&self`, and shared with
+    /// the offscreen surfaces drawn on it. The slot is only empty for the duration of
+    /// `make_current`.
+    context: Rc<GlContextSlot>,
+    winit_window: winit::window::Window,
 }
 impl GlRenderer {
     pub fn new<E>(
         window_builder: winit::window::WindowBuilder,
         event_loop: &EventLoopWindowTarget<E>,
-    ) -> Self {
+    ) -> Result<Self, RendererInitError> {
+        Self::with_config(window_builder, event_loop, GlConfig::default())
+    }
+    pub fn with_config<E>(
+        window_builder: winit::window::WindowBuilder,
+        event_loop: &EventLoopWindowTarget<E>,
+        config: GlConfig,
+    ) -> Result<Self, RendererInitError> {
+        Self::build(window_builder, event_loop, config, None)
+    }
+    /// Open another window whose GL context shares textures and buffers with `shared`'s, using
+    /// the same `GlConfig` so the pixel formats are compatible. Each renderer still gets its own
+    /// Skia context, so GPU-backed Skia images must only be drawn by the renderer that made them.
+    ///
+    /// Only one context can be current at a time, so every renderer makes its own context current
+    /// before touching GL. Everything has to stay on the thread that created the renderers.
+    pub fn new_shared<E>(
+        window_builder: winit::window::WindowBuilder,
+        event_loop: &EventLoopWindowTarget<E>,
+        shared: &GlRenderer,
+    ) -> Result<Self, RendererInitError> {
+        Self::build(
+            window_builder,
+            event_loop,
+            shared.config.clone(),
+            Some(shared),
+        )
+    }
+    fn build<E>(
+        window_builder: winit::window::WindowBuilder,
+        event_loop: &EventLoopWindowTarget<E>,
+        config: GlConfig,
+        shared: Option<&GlRenderer>,
+    ) -> Result<Self, RendererInitError> {
+        let requested_size = window_builder.window.inner_size;
+        let shared_context = shared.map(GlRenderer::context);
+        let build_with_stencil =
+            |profile: GlProfile, stencil_bits: u8, window_builder: winit::window::WindowBuilder| {
+                let mut cb = glutin::ContextBuilder::new()
+                    .with_depth_buffer(config.depth_bits)
+                    .with_stencil_buffer(stencil_bits)
+                    .with_pixel_format(config.color_bits, config.alpha_bits)
+                    .with_float_color_buffer(config.color_type == skia_safe::ColorType::RGBAF16)
+                    .with_double_buffer(config.buffering.double_buffer())
+                    .with_vsync(config.present_mode == PresentMode::Vsync)
+                    .with_gl_profile(profile.to_glutin());
+                if let Some(version) = config.gl_version {
+                    cb = cb.with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, version));
+                }
+                if let Some(samples) = config.msaa_samples {
+                    cb = cb.with_multisampling(samples);
+                }
+                match &shared_context {
+                    Some(shared_context) => cb
+                        .with_shared_lists(shared_context.context())
+                        .build_windowed(window_builder, &event_loop),
+                    None => cb.build_windowed(window_builder, &event_loop),
+                }
+            };
+
+        // Some remote and virtual drivers have no pixel format with a stencil buffer. Skia is told
+        // the stencil bits the pixel format has, and draws complex clips without one, more slowly
+        // and with coarser anti-aliasing.
+        let build_windowed = |profile: GlProfile, window_builder: winit::window::WindowBuilder| {
+            match build_with_stencil(profile, config.stencil_bits, window_builder.clone()) {
+                Err(e)
+                    if config.stencil_bits > 0
+                        && !matches!(e, glutin::CreationError::Window(_)) =>
+                {
+                    log::warn!(
+                        "Error during context creation with {} stencil bits: {:?}, \
+                         Retrying without a stencil buffer.",
+                        config.stencil_bits,
+                        e
+                    );
+                    build_with_stencil(profile, 0, window_builder)
+                }
+                result => result,
+            }
+        };
+        let windowed_context = match build_windowed(config.gl_profile, window_builder.clone()) {
+            Err(e)
+                if config.compatibility_fallback
+                    && config.gl_profile == GlProfile::Core
+                    && !matches!(e, glutin::CreationError::Window(_)) =>
+            {
+                log::warn!(
+                    "Error during core profile context creation: {:?}, Using compatibility.",
+                    e
+                );
+                build_windowed(GlProfile::Compatibility, window_builder)
+            }
+            result => result,
+        }
+        .map_err(RendererInitError::GlCreation)?;
+        let windowed_context = unsafe { windowed_context.make_current() }
+            .map_err(|(_, e)| RendererInitError::GlContext(e))?;
+        // The window is stored after the context, so it's dropped last.
+        let (context, winit_window) = unsafe { windowed_context.split() };
+        Self::from_parts(context, winit_window, config, requested_size)
+    }
+    /// Render into a context and window created elsewhere, e.g. by an embedding application that
+    /// keeps ownership of its own window setup. Skia renders into the framebuffer bound when this
+    /// is called, at the window's current size. The context should have been created with at
+    /// least 24 color bits and a stencil buffer.
+    pub fn from_context(
+        context: glutin::WindowedContext<glutin::PossiblyCurrent>,
+    ) -> Result<Self, RendererInitError> {
+        Self::from_context_with_config(context, GlConfig::default())
+    }
+    /// Like `from_context`, but with the coordinate system, color type and surface options in
+    /// `config`. Its pixel format and context options are ignored, since the context already
+    /// exists.
+    pub fn from_context_with_config(
+        context: glutin::WindowedContext<glutin::PossiblyCurrent>,
+        config: GlConfig,
+    ) -> Result<Self, RendererInitError> {
+        let context =
+            unsafe { context.make_current() }.map_err(|(_, e)| RendererInitError::GlContext(e))?;
+        let (context, winit_window) = unsafe { context.split() };
+        Self::from_parts(context, winit_window, config, None)
+    }
+    fn from_parts(
+        context: glutin::RawContext<glutin::PossiblyCurrent>,
+        winit_window: winit::window::Window,
+        config: GlConfig,
+        requested_size: Option<winit::dpi::Size>,
+    ) -> Result<Self, RendererInitError> {
         use gl::types::*;
 
-        let cb = glutin::ContextBuilder::new()
-            .with_depth_buffer(0)
-            .with_stencil_buffer(8)
-            .with_pixel_format(24, 8)
-            .with_double_buffer(Some(true))
-            .with_gl_profile(glutin::GlProfile::Core);
+        let pixel_format = context.get_pixel_format();
+        if pixel_format.stencil_bits < config.stencil_bits {
+            log::warn!(
+                "Requested {} stencil bits but got {}, complex clips may render incorrectly.",
+                config.stencil_bits,
+                pixel_format.stencil_bits
+            );
+        }
 
-        let windowed_context = cb.build_windowed(window_builder, &event_loop).unwrap();
-        let windowed_context = unsafe { windowed_context.make_current().unwrap() };
+        let format = match gl_format(config.color_type) {
+            Some((format, color_bits, _))
+                if pixel_format.color_bits >= color_bits
+                    && (config.color_type != skia_safe::ColorType::RGBAF16
+                        || pixel_format.float_color_buffer) =>
+            {
+                format
+            }
+            _ => return Err(RendererInitError::UnsupportedColorType(config.color_type)),
+        };
 
-        let pixel_format = windowed_context.get_pixel_format();
+        gl::load_with(|s| context.get_proc_address(&s));
 
-        gl::load_with(|s| windowed_context.get_proc_address(&s));
+        let mut max_surface_size: GLint = 0;
+        unsafe { gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_surface_size) };
 
-        let mut gr_context = skia_safe::gpu::Context::new_gl(None).unwrap();
+        let mut gr_context =
+            skia_safe::gpu::Context::new_gl(None).ok_or(RendererInitError::SkiaContext)?;
 
         let mut fboid: GLint = 0;
         unsafe { gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut fboid) };
 
         let fb_info = skia_safe::gpu::gl::FramebufferInfo {
-            fboid: fboid.try_into().unwrap(),
-            format: skia_safe::gpu::gl::Format::RGBA8.into(),
+            fboid: config
+                .framebuffer
+                .unwrap_or_else(|| fboid.try_into().unwrap()),
+            format: format.into(),
         };
 
-        let size = windowed_context.window().inner_size();
-        let backend_render_target = skia_safe::gpu::BackendRenderTarget::new_gl(
-            (
-                size.width.try_into().unwrap(),
-                size.height.try_into().unwrap(),
-            ),
-            pixel_format.multisampling.map(|s| s.try_into().unwrap()),
-            pixel_format.stencil_bits.try_into().unwrap(),
-            fb_info,
-        );
-        let mut surface = skia_safe::Surface::from_backend_render_target(
-            &mut gr_context,
-            &backend_render_target,
-            skia_safe::gpu::SurfaceOrigin::BottomLeft,
-            skia_safe::ColorType::RGBA8888,
-            None,
-            None,
-        )
-        .unwrap();
+        log_size_mismatch(requested_size, &winit_window);
+        let size = winit_window.inner_size();
+        context.resize(size);
+        let backend_render_target = Self::new_backend_render_target(size, &pixel_format, fb_info);
+        let mut surface = Self::new_surface(&mut gr_context, &backend_render_target, &config)
+            .ok_or(RendererInitError::SkiaSurface)?;
 
-        let sf = windowed_context.window().scale_factor() as f32;
-        surface.canvas().scale((sf, sf));
-        Self {
-            windowed_context,
+        apply_coordinate_system(
+            &mut surface,
+            config.coordinate_system,
+            winit_window.scale_factor(),
+        );
+        Ok(Self {
+            context: Rc::new(RefCell::new(Some(context))),
+            config,
             gr_context: RefCell::new(gr_context),
             fb_info,
             backend_render_target: RefCell::new(backend_render_target),
             surface: RefCell::new(surface),
+            max_surface_size: max_surface_size.try_into().unwrap(),
+            frame_state: FrameState::default(),
+            winit_window,
+        })
+    }
+    fn context(&self) -> Ref<glutin::RawContext<glutin::PossiblyCurrent>> {
+        Ref::map(self.context.borrow(), |context| context.as_ref().unwrap())
+    }
+    /// Make this renderer's context current if another renderer on the thread took over.
+    fn make_current(&self) -> Result<(), glutin::ContextError> {
+        make_context_current(&self.context)
+    }
+    /// `make_current` for paths that can't report errors; a failure surfaces on the next paint.
+    fn bind(&self) {
+        if let Err(e) = self.make_current() {
+            log::warn!("Error making the OpenGL context current: {:?}", e);
         }
     }
     pub fn resize(&self, size: PhysicalSize<u32>) {
-        self.windowed_context.resize(size);
-
-        let pixel_format = self.windowed_context.get_pixel_format();
+        if let Err(e) = self.try_resize(size) {
+            log::error!("Error resizing the OpenGL surface: {:?}", e);
+        }
+    }
+    /// Sizes above `max_surface_size` are rejected with `ResizeError::TooLarge`, keeping the old
+    /// surface.
+    pub fn try_resize(&self, size: PhysicalSize<u32>) -> Result<(), ResizeError> {
+        // Minimizing reports a zero size; keep the old surface until the window is restored.
+        if size.width == 0 || size.height == 0 {
+            return Ok(());
+        }
+        check_surface_size(size, self.max_surface_size)?;
+        self.context().resize(size);
+        self.rebuild_surface(size).map_err(ResizeError::Surface)?;
 
-        *self.backend_render_target.borrow_mut() = skia_safe::gpu::BackendRenderTarget::new_gl(
+        self.winit_window.request_redraw();
+        Ok(())
+    }
+    pub fn max_surface_size(&self) -> u32 {
+        self.max_surface_size
+    }
+    /// Rebuild the render target and surface at the current window size, for when they went
+    /// stale for reasons other than a resize, such as the framebuffer being rebound externally.
+    pub fn recreate_surface(&self) -> Result<(), RendererInitError> {
+        self.rebuild_surface(self.winit_window.inner_size())
+    }
+    fn rebuild_surface(&self, size: PhysicalSize<u32>) -> Result<(), RendererInitError> {
+        self.make_current().map_err(RendererInitError::GlContext)?;
+        let pixel_format = self.pixel_format();
+        let backend_render_target =
+            Self::new_backend_render_target(size, &pixel_format, self.fb_info);
+        let mut surface = Self::new_surface(
+            &mut self.gr_context.borrow_mut(),
+            &backend_render_target,
+            &self.config,
+        )
+        .ok_or(RendererInitError::SkiaSurface)?;
+        apply_coordinate_system(
+            &mut surface,
+            self.config.coordinate_system,
+            self.scale_factor(),
+        );
+        *self.backend_render_target.borrow_mut() = backend_render_target;
+        *self.surface.borrow_mut() = surface;
+        *self.frame_state.last_frame.borrow_mut() = None;
+        Ok(())
+    }
+    /// The scale is re-applied from the window at the start of every paint, like Skulpin does.
+    pub fn set_scale_factor(&self, _scale_factor: f64) {
+        self.winit_window.request_redraw();
+    }
+    fn new_backend_render_target(
+        size: PhysicalSize<u32>,
+        pixel_format: &glutin::PixelFormat,
+        fb_info: skia_safe::gpu::gl::FramebufferInfo,
+    ) -> skia_safe::gpu::BackendRenderTarget {
+        skia_safe::gpu::BackendRenderTarget::new_gl(
             (
                 size.width.try_into().unwrap(),
                 size.height.try_into().unwrap(),
             ),
             pixel_format.multisampling.map(|s| s.try_into().unwrap()),
             pixel_format.stencil_bits.try_into().unwrap(),
-            self.fb_info,
+            fb_info,
+        )
+    }
+    fn new_surface(
+        gr_context: &mut skia_safe::gpu::Context,
+        backend_render_target: &skia_safe::gpu::BackendRenderTarget,
+        config: &GlConfig,
+    ) -> Option<skia_safe::Surface> {
+        skia_safe::Surface::from_backend_render_target(
+            gr_context,
+            backend_render_target,
+            config.surface_origin,
+            config.color_type,
+            config.color_space.clone(),
+            config.surface_props.as_ref(),
+        )
+    }
+    /// If presenting reports `ContextError::ContextLost`, the Skia context, render target and
+    /// surface are rebuilt on the existing GL context, a repaint is requested and
+    /// `PaintError::GlContextRecovered` is returned, since the closure has already been consumed.
+    ///
+    /// glutin can only recreate a context together with its window, so when the driver doesn't
+    /// restore the GL context itself the rebuild fails and `PaintError::GlContextLost` is
+    /// returned. Other `ContextError` kinds are returned as `PaintError::Gl` without recovery.
+    pub fn paint<F: FnOnce(&mut skia_safe::Canvas)>(&self, f: F) -> Result<(), PaintError> {
+        self.paint_surface(|surface| f(surface.canvas()))
+    }
+    pub fn paint_surface<F: FnOnce(&mut skia_safe::Surface)>(
+        &self,
+        f: F,
+    ) -> Result<(), PaintError> {
+        self.present(Swap::Full, f)
+    }
+    /// Falls back to a full swap when the driver can't swap with damage.
+    pub fn paint_region<F: FnOnce(&mut skia_safe::Canvas)>(
+        &self,
+        damage: skia_safe::IRect,
+        f: F,
+    ) -> Result<(), PaintError> {
+        self.present(Swap::Damage(damage), |surface| f(surface.canvas()))
+    }
+    pub fn paint_no_present<F: FnOnce(&mut skia_safe::Canvas)>(
+        &self,
+        f: F,
+    ) -> Result<(), PaintError> {
+        self.present(Swap::Skip, |surface| f(surface.canvas()))
+    }
+    pub fn swap_buffers(&self) -> Result<(), PaintError> {
+        self.make_current()
+            .and_then(|()| {
+                if self.is_double_buffered() {
+                    self.capture_frame(&mut self.surface.borrow_mut(), true);
+                    self.flush();
+                    self.context().swap_buffers()
+                } else {
+                    unsafe { gl::Flush() };
+                    Ok(())
+                }
+            })
+            .map_err(PaintError::Gl)
+    }
+    fn begin_paint(&self) -> Result<RefMut<skia_safe::Surface>, PaintError> {
+        self.make_current().map_err(PaintError::Gl)?;
+        let mut surface = self.surface.borrow_mut();
+        apply_coordinate_system(
+            &mut surface,
+            self.config.coordinate_system,
+            self.scale_factor(),
         );
-        *self.surface.borrow_mut() = skia_safe::Surface::from_backend_render_target(
+        Ok(surface)
+    }
+    fn present<F: FnOnce(&mut skia_safe::Surface)>(
+        &self,
+        swap: Swap,
+        f: F,
+    ) -> Result<(), PaintError> {
+        let size = self.winit_window.inner_size();
+        {
+            let surface = self.surface.borrow();
+            if (surface.width() as u32, surface.height() as u32) != (size.width, size.height) {
+                return Err(PaintError::SurfaceOutOfDate);
+            }
+        }
+        let result = self.make_current().and_then(|()| {
+            let mut surface = self.surface.borrow_mut();
+            apply_coordinate_system(
+                &mut surface,
+                self.config.coordinate_system,
+                self.scale_factor(),
+            );
+            f(&mut surface);
+            let swaps = self.is_double_buffered() && !matches!(swap, Swap::Skip);
+            self.capture_frame(&mut surface, swaps);
+            self.flush();
+            let height = surface.height();
+            let context = self.context();
+            match swap {
+                Swap::Skip => Ok(()),
+                // Single-buffered drawing goes straight to the front buffer, there's nothing to
+                // swap.
+                _ if !context.get_pixel_format().double_buffer => {
+                    unsafe { gl::Flush() };
+                    Ok(())
+                }
+                Swap::Damage(damage) if context.swap_buffers_with_damage_supported() => {
+                    // Damage rects are measured from the bottom left like the rest of GL.
+                    let damage = skia_safe::IRect::intersect(
+                        &skia_safe::IRect::from_wh(surface.width(), height),
+                        &damage,
+                    )
+                    .unwrap_or_default();
+                    context.swap_buffers_with_damage(&[glutin::Rect {
+                        x: damage.left as u32,
+                        y: (height - damage.bottom) as u32,
+                        width: damage.width() as u32,
+                        height: damage.height() as u32,
+                    }])
+                }
+                _ => context.swap_buffers(),
+            }
+        });
+        match result {
+            Err(glutin::ContextError::ContextLost) => {
+                self.recover_lost_context()
+                    .map_err(|_| PaintError::GlContextLost)?;
+                self.request_repaint();
+                Err(PaintError::GlContextRecovered)
+            }
+            result => result.map_err(PaintError::Gl),
+        }
+    }
+    fn recover_lost_context(&self) -> Result<(), RendererInitError> {
+        self.gr_context.borrow_mut().abandon();
+        *self.gr_context.borrow_mut() =
+            skia_safe::gpu::Context::new_gl(None).ok_or(RendererInitError::SkiaContext)?;
+        self.recreate_surface()
+    }
+    /// Keep a copy of the frame for `snapshot` if it's about to be swapped, since the back
+    /// buffer's contents are undefined afterwards. The copy is recorded before `flush` so the GPU
+    /// makes it ahead of the swap.
+    fn capture_frame(&self, surface: &mut skia_safe::Surface, swaps: bool) {
+        *self.frame_state.last_frame.borrow_mut() = if swaps {
+            Some(surface.image_snapshot())
+        } else {
+            None
+        };
+    }
+    /// The last presented frame, or the surface's contents if nothing has been swapped since
+    /// they were drawn, e.g. after `paint_no_present`.
+    pub fn snapshot(&self) -> skia_safe::Image {
+        if let Some(image) = &*self.frame_state.last_frame.borrow() {
+            return image.clone();
+        }
+        self.bind();
+        self.surface.borrow_mut().image_snapshot()
+    }
+    /// Reads from the same frame `snapshot` returns.
+    pub fn read_pixels(&self, rect: skia_safe::IRect) -> Option<Vec<u8>> {
+        self.bind();
+        match &*self.frame_state.last_frame.borrow() {
+            Some(image) => read_image_pixels(image, rect),
+            None => read_surface_pixels(&mut self.surface.borrow_mut(), rect),
+        }
+    }
+    pub fn characterize(&self) -> Option<skia_safe::SurfaceCharacterization> {
+        self.surface.borrow().characterize()
+    }
+    pub fn adapter_info(&self) -> AdapterInfo {
+        self.bind();
+        AdapterInfo {
+            name: gl_string(unsafe { gl::GetString(gl::RENDERER) }).unwrap_or_default(),
+            backend: Backend::OpenGl,
+            driver: gl_string(unsafe { gl::GetString(gl::VERSION) }),
+        }
+    }
+    pub fn create_offscreen(&self, size: skia_safe::ISize) -> Option<OffscreenSurface> {
+        self.bind();
+        skia_safe::Surface::new_render_target(
             &mut self.gr_context.borrow_mut(),
-            &self.backend_render_target.borrow(),
-            skia_safe::gpu::SurfaceOrigin::BottomLeft,
-            skia_safe::ColorType::RGBA8888,
+            skia_safe::Budgeted::Yes,
+            &skia_safe::ImageInfo::new_n32_premul(size, None),
             None,
+            skia_safe::gpu::SurfaceOrigin::TopLeft,
             None,
+            false,
         )
-        .unwrap();
-
-        self.windowed_context.window().request_redraw();
+        .map(|surface| OffscreenSurface::with_context(surface, Rc::downgrade(&self.context)))
     }
-    pub fn paint<F: FnOnce(&mut skia_safe::Canvas)>(
-        &self,
-        f: F,
-    ) -> Result<(), glutin::ContextError> {
-        let mut surface = self.surface.borrow_mut();
-        let mut canvas = surface.canvas();
-        f(&mut canvas);
-        canvas.flush();
-        self.windowed_context.swap_buffers()
+    /// The pixel format that was actually negotiated with the driver.
+    pub fn pixel_format(&self) -> glutin::PixelFormat {
+        self.context().get_pixel_format()
+    }
+    /// The framebuffer Skia renders into, for callers that bind or blit to it with their own GL
+    /// calls. Those calls must happen on the thread that created the renderer while its context
+    /// is current, which `with_gpu_context` ensures, and Skia's cached GL state should be reset
+    /// afterwards with `with_gpu_context(|context| context.reset(None))`.
+    pub fn framebuffer_info(&self) -> skia_safe::gpu::gl::FramebufferInfo {
+        self.fb_info
+    }
+    /// Looks up a GL function on this renderer's context, for initializing external GL loaders.
+    /// The returned pointers are only valid while this context is current. Other renderers and
+    /// `available_gl_pixel_formats` make their own contexts current on the same thread, so check
+    /// `is_current`, or call GL from inside `with_gpu_context`, which makes this context current
+    /// first.
+    pub fn get_proc_address(&self, name: &str) -> *const std::ffi::c_void {
+        self.context().get_proc_address(name)
+    }
+    /// The stencil bits the driver actually provided. Skia draws complex clips with the stencil
+    /// buffer, so fewer bits than `GlConfig::stencil_bits` can make them glitch.
+    pub fn stencil_bits(&self) -> u8 {
+        self.pixel_format().stencil_bits
+    }
+    pub fn depth_bits(&self) -> u8 {
+        self.pixel_format().depth_bits
+    }
+    /// Compare with `GlConfig::msaa_samples` to tell whether the request was honored.
+    pub fn msaa_samples(&self) -> Option<u32> {
+        self.pixel_format()
+            .multisampling
+            .filter(|&samples| samples > 0)
+            .map(u32::from)
+    }
+    /// Whether the context is current on this thread. It's made current before every paint, so
+    /// this is mostly useful for checking with other GL code that shares the thread.
+    pub fn is_current(&self) -> bool {
+        self.context().is_current()
+    }
+    pub fn is_double_buffered(&self) -> bool {
+        self.pixel_format().double_buffer
+    }
+    /// The extension names the context reports, e.g. `GL_ARB_texture_storage`.
+    pub fn extensions(&self) -> HashSet<String> {
+        self.bind();
+        // glGetStringi is OpenGL 3.0; older contexts list every extension in one string.
+        if gl::GetStringi::is_loaded() {
+            let mut count: gl::types::GLint = 0;
+            unsafe { gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count) };
+            (0..count.max(0) as gl::types::GLuint)
+                .filter_map(|i| gl_string(unsafe { gl::GetStringi(gl::EXTENSIONS, i) }))
+                .collect()
+        } else {
+            gl_string(unsafe { gl::GetString(gl::EXTENSIONS) })
+                .map(|extensions| extensions.split_whitespace().map(String::from).collect())
+                .unwrap_or_default()
+        }
+    }
+    pub fn capabilities(&self) -> GlCapabilities {
+        let get_integer = |name| {
+            let mut value: gl::types::GLint = 0;
+            unsafe { gl::GetIntegerv(name, &mut value) };
+            value.max(0) as u32
+        };
+        self.bind();
+        GlCapabilities {
+            swap_buffers_with_damage: self.context().swap_buffers_with_damage_supported(),
+            max_texture_size: get_integer(gl::MAX_TEXTURE_SIZE),
+            max_samples: get_integer(gl::MAX_SAMPLES),
+        }
+    }
+    pub fn with_gpu_context<R>(&self, f: impl FnOnce(&mut skia_safe::gpu::Context) -> R) -> R {
+        self.bind();
+        f(&mut self.gr_context.borrow_mut())
+    }
+    pub fn flush(&self) {
+        self.bind();
+        self.gr_context.borrow_mut().flush_and_submit();
+    }
+    /// Release everything in order with the context current: Skia's surfaces, then the Skia
+    /// context, which is abandoned after freeing its GPU resources, then the GL context and the
+    /// window. Plain dropping follows the same order but can't make the context current first.
+    pub fn shutdown(self) {
+        self.bind();
+        let Self {
+            frame_state,
+            surface,
+            backend_render_target,
+            gr_context,
+            context,
+            winit_window,
+            ..
+        } = self;
+        drop(frame_state);
+        drop(surface);
+        drop(backend_render_target);
+        let mut gr_context = gr_context.into_inner();
+        gr_context.flush_and_submit();
+        gr_context.release_resources_and_abandon();
+        drop(gr_context);
+        drop(context);
+        drop(winit_window);
+    }
+    /// Block until the GPU has finished all submitted work, with a fence where the driver
+    /// supports them (OpenGL 3.2 or ARB_sync) and `glFinish` otherwise.
+    pub fn wait_idle(&self) {
+        self.bind();
+        unsafe {
+            if gl::FenceSync::is_loaded() {
+                let sync = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+                gl::ClientWaitSync(sync, gl::SYNC_FLUSH_COMMANDS_BIT, gl::TIMEOUT_IGNORED);
+                gl::DeleteSync(sync);
+            } else {
+                gl::Finish();
+            }
+        }
     }
     pub fn request_repaint(&self) {
-        self.windowed_context.window().request_redraw()
+        self.winit_window.request_redraw()
     }
     pub fn scale_factor(&self) -> f64 {
-        self.windowed_context.window().scale_factor()
+        self.winit_window.scale_factor()
+    }
+    pub fn window(&self) -> &winit::window::Window {
+        &self.winit_window
+    }
+}
+
+type GlContextSlot = RefCell<Option<glutin::RawContext<glutin::PossiblyCurrent>>>;
+
+/// Make the context in `slot` current unless it already is, putting it back either way.
+fn make_context_current(slot: &GlContextSlot) -> Result<(), glutin::ContextError> {
+    let mut slot = slot.borrow_mut();
+    if slot.as_ref().unwrap().is_current() {
+        return Ok(());
+    }
+    let (context, result) = match unsafe { slot.take().unwrap().make_current() } {
+        Ok(context) => (context, Ok(())),
+        Err((context, e)) => (context, Err(e)),
+    };
+    *slot = Some(context);
+    result
+}
+
+fn canvas_scale(coordinate_system: CoordinateSystem, scale_factor: f64, content_scale: f32) -> f32 {
+    let base = match coordinate_system {
+        CoordinateSystem::Logical => scale_factor as f32,
+        CoordinateSystem::Physical => 1.0,
+    };
+    base * content_scale
+}
+
+/// Copies a string returned by `glGetString` or `glGetStringi`, which is null on error.
+fn gl_string(string: *const gl::types::GLubyte) -> Option<String> {
+    if string.is_null() {
+        None
+    } else {
+        let string = unsafe { CStr::from_ptr(string as *const _) };
+        Some(string.to_string_lossy().into_owned())
+    }
+}
+
+fn check_surface_size(size: PhysicalSize<u32>, max: u32) -> Result<(), ResizeError> {
+    if size.width > max || size.height > max {
+        return Err(ResizeError::TooLarge {
+            requested: size,
+            max,
+        });
+    }
+    Ok(())
+}
+
+fn apply_coordinate_system(
+    surface: &mut skia_safe::Surface,
+    coordinate_system: CoordinateSystem,
+    scale_factor: f64,
+) {
+    let canvas = surface.canvas();
+    canvas.reset_matrix();
+    if coordinate_system == CoordinateSystem::Logical {
+        let sf = scale_factor as f32;
+        canvas.scale((sf, sf));
+    }
+}
+
+/// Window managers may ignore the requested size, so surfaces are always sized from the window.
+fn log_size_mismatch(requested: Option<winit::dpi::Size>, window: &winit::window::Window) {
+    let actual = window.inner_size();
+    if let Some(requested) = requested.map(|size| size.to_physical(window.scale_factor())) {
+        if requested != actual {
+            log::debug!(
+                "Requested a {}x{} window but got {}x{}.",
+                requested.width,
+                requested.height,
+                actual.width,
+                actual.height
+            );
+        }
+    }
+}
+
+/// The framebuffer format and the `(color_bits, alpha_bits)` to request for a Skia color type
+/// on OpenGL.
+fn gl_format(color_type: skia_safe::ColorType) -> Option<(skia_safe::gpu::gl::Format, u8, u8)> {
+    use skia_safe::{gpu::gl::Format, ColorType};
+
+    match color_type {
+        ColorType::RGBA8888 => Some((Format::RGBA8, 24, 8)),
+        ColorType::BGRA8888 => Some((Format::BGRA8, 24, 8)),
+        ColorType::RGBA1010102 => Some((Format::RGB10_A2, 30, 2)),
+        ColorType::RGBAF16 => Some((Format::RGBA16F, 48, 16)),
+        _ => None,
+    }
+}
+
+fn read_surface_pixels(
+    surface: &mut skia_safe::Surface,
+    rect: skia_safe::IRect,
+) -> Option<Vec<u8>> {
+    let size = skia_safe::ISize::new(surface.width(), surface.height());
+    read_rect_pixels(size, rect, |info, pixels, row_bytes, origin| {
+        surface.read_pixels(info, pixels, row_bytes, origin)
+    })
+}
+
+fn read_image_pixels(image: &skia_safe::Image, rect: skia_safe::IRect) -> Option<Vec<u8>> {
+    read_rect_pixels(
+        image.dimensions(),
+        rect,
+        |info, pixels, row_bytes, origin| {
+            image.read_pixels(
+                info,
+                pixels,
+                row_bytes,
+                origin,
+                skia_safe::image::CachingHint::Allow,
+            )
+        },
+    )
+}
+
+/// Checks `rect` against `size` and reads it as unpremultiplied RGBA8 with `read`.
+fn read_rect_pixels(
+    size: skia_safe::ISize,
+    rect: skia_safe::IRect,
+    read: impl FnOnce(&skia_safe::ImageInfo, &mut [u8], usize, skia_safe::IPoint) -> bool,
+) -> Option<Vec<u8>> {
+    if rect.is_empty()
+        || rect.left < 0
+        || rect.top < 0
+        || rect.right > size.width
+        || rect.bottom > size.height
+    {
+        return None;
+    }
+    let info = skia_safe::ImageInfo::new(
+        rect.size(),
+        skia_safe::ColorType::RGBA8888,
+        skia_safe::AlphaType::Unpremul,
+        None,
+    );
+    let row_bytes = info.min_row_bytes();
+    let mut pixels = vec![0; row_bytes * rect.height() as usize];
+    if read(
+        &info,
+        &mut pixels,
+        row_bytes,
+        skia_safe::IPoint::new(rect.left, rect.top),
+    ) {
+        Some(pixels)
+    } else {
+        None
+    }
+}
+
+fn save_image_png(image: &skia_safe::Image, path: &Path) -> Result<(), SaveError> {
+    let data = image
+        .encode_to_data(skia_safe::EncodedImageFormat::PNG)
+        .ok_or(SaveError::Encode)?;
+    std::fs::write(path, data.as_bytes()).map_err(SaveError::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_scale_multiplies_the_coordinate_scale() {
+        assert_eq!(canvas_scale(CoordinateSystem::Logical, 2.0, 1.0), 2.0);
+        assert_eq!(canvas_scale(CoordinateSystem::Logical, 2.0, 1.5), 3.0);
+        assert_eq!(canvas_scale(CoordinateSystem::Logical, 1.5, 0.5), 0.75);
+        assert_eq!(canvas_scale(CoordinateSystem::Physical, 2.0, 1.5), 1.5);
+    }
+
+    #[test]
+    fn coordinate_system_follows_scale_factor_changes() {
+        let info = skia_safe::ImageInfo::new_n32_premul((16, 16), None);
+        let mut surface = skia_safe::Surface::new_raster(&info, None, None).unwrap();
+        for &scale_factor in &[1.0, 2.0, 1.5, 1.0] {
+            apply_coordinate_system(&mut surface, CoordinateSystem::Logical, scale_factor);
+            let matrix = surface.canvas().total_matrix();
+            assert_eq!(matrix.scale_x(), scale_factor as f32);
+            assert_eq!(matrix.scale_y(), scale_factor as f32);
+        }
+        apply_coordinate_system(&mut surface, CoordinateSystem::Physical, 2.0);
+        assert_eq!(surface.canvas().total_matrix().scale_x(), 1.0);
+    }
+
+    #[test]
+    fn oversized_surfaces_are_too_large() {
+        assert!(check_surface_size(PhysicalSize::new(4096, 4096), 4096).is_ok());
+        for &size in &[PhysicalSize::new(4097, 100), PhysicalSize::new(100, 16384)] {
+            match check_surface_size(size, 4096) {
+                Err(ResizeError::TooLarge { requested, max }) => {
+                    assert_eq!(requested, size);
+                    assert_eq!(max, 4096);
+                }
+                result => panic!("expected TooLarge, got {:?}", result),
+            }
+        }
     }
 }
@@ -0,0 +1,257 @@
+use {
+    crate::{
+        apply_coordinate_system, check_surface_size, log_size_mismatch, winit, CoordinateSystem,
+        FrameState, RendererInitError, ResizeError,
+    },
+    raw_window_handle::HasRawWindowHandle,
+    skulpin::winit::{dpi::PhysicalSize, event_loop::EventLoopWindowTarget},
+    std::cell::{RefCell, RefMut},
+};
+
+/// softbuffer takes the buffer's width and height as `u16`.
+const MAX_SURFACE_SIZE: u32 = u16::MAX as u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RasterConfig {
+    pub coordinate_system: CoordinateSystem,
+}
+
+/// Draws into a CPU raster surface and copies it to the window with softbuffer. This is the last
+/// resort when neither Vulkan nor OpenGL are available.
+pub struct RasterRenderer {
+    graphics_context: RefCell<softbuffer::GraphicsContext<WindowHandle>>,
+    surface: RefCell<skia_safe::Surface>,
+    /// The surface converted for softbuffer, kept between presents to reuse its allocation.
+    buffer: RefCell<Vec<u32>>,
+    winit_window: winit::window::Window,
+    config: RasterConfig,
+    pub(crate) frame_state: FrameState,
+}
+impl RasterRenderer {
+    pub fn new<E>(
+        window_builder: winit::window::WindowBuilder,
+        event_loop: &EventLoopWindowTarget<E>,
+    ) -> Result<Self, RendererInitError> {
+        Self::with_config(window_builder, event_loop, RasterConfig::default())
+    }
+    pub fn with_config<E>(
+        window_builder: winit::window::WindowBuilder,
+        event_loop: &EventLoopWindowTarget<E>,
+        config: RasterConfig,
+    ) -> Result<Self, RendererInitError> {
+        let requested_size = window_builder.window.inner_size;
+        let winit_window = window_builder
+            .build(&event_loop)
+            .map_err(RendererInitError::Window)?;
+        log_size_mismatch(requested_size, &winit_window);
+        let size = winit_window.inner_size();
+        check_size(size)?;
+        let handle = WindowHandle::new(&winit_window).ok_or(RendererInitError::Raster(None))?;
+        // softbuffer's error holds the window handle, which isn't `Send`, so only its message is
+        // kept.
+        let graphics_context = unsafe { softbuffer::GraphicsContext::new(handle) }
+            .map_err(|e| RendererInitError::Raster(Some(e.to_string().into())))?;
+        let mut surface = Self::new_surface(size).ok_or(RendererInitError::SkiaSurface)?;
+        apply_coordinate_system(
+            &mut surface,
+            config.coordinate_system,
+            winit_window.scale_factor(),
+        );
+
+        Ok(Self {
+            graphics_context: RefCell::new(graphics_context),
+            surface: RefCell::new(surface),
+            buffer: RefCell::default(),
+            winit_window,
+            config,
+            frame_state: FrameState::default(),
+        })
+    }
+    fn new_surface(size: PhysicalSize<u32>) -> Option<skia_safe::Surface> {
+        let info = skia_safe::ImageInfo::new(
+            (size.width.max(1) as i32, size.height.max(1) as i32),
+            skia_safe::ColorType::BGRA8888,
+            skia_safe::AlphaType::Premul,
+            None,
+        );
+        skia_safe::Surface::new_raster(&info, None, None)
+    }
+    pub fn resize(&self, size: PhysicalSize<u32>) {
+        if let Err(e) = self.try_resize(size) {
+            log::error!("Error resizing the raster surface: {:?}", e);
+        }
+    }
+    /// Sides longer than 65535 pixels, which softbuffer can't present, are rejected with
+    /// `ResizeError::TooLarge`, keeping the old surface.
+    pub fn try_resize(&self, size: PhysicalSize<u32>) -> Result<(), ResizeError> {
+        if size.width == 0 || size.height == 0 {
+            return Ok(());
+        }
+        check_surface_size(size, MAX_SURFACE_SIZE)?;
+        self.rebuild_surface(size).map_err(ResizeError::Surface)?;
+
+        self.winit_window.request_redraw();
+        Ok(())
+    }
+    pub fn recreate_surface(&self) -> Result<(), RendererInitError> {
+        self.rebuild_surface(self.winit_window.inner_size())
+    }
+    fn rebuild_surface(&self, size: PhysicalSize<u32>) -> Result<(), RendererInitError> {
+        check_size(size)?;
+        let mut surface = Self::new_surface(size).ok_or(RendererInitError::SkiaSurface)?;
+        apply_coordinate_system(
+            &mut surface,
+            self.config.coordinate_system,
+            self.scale_factor(),
+        );
+        *self.surface.borrow_mut() = surface;
+        Ok(())
+    }
+    pub fn paint<F: FnOnce(&mut skia_safe::Canvas)>(&self, f: F) {
+        self.paint_surface(|surface| f(surface.canvas()))
+    }
+    pub fn paint_surface<F: FnOnce(&mut skia_safe::Surface)>(&self, f: F) {
+        let mut surface = self.surface.borrow_mut();
+        apply_coordinate_system(
+            &mut surface,
+            self.config.coordinate_system,
+            self.scale_factor(),
+        );
+        f(&mut surface);
+
+        let (width, height) = (surface.width(), surface.height());
+        let pixmap = match surface.peek_pixels() {
+            Some(pixmap) => pixmap,
+            None => return,
+        };
+        let pixels = match pixmap.bytes() {
+            Some(pixels) => pixels,
+            None => return,
+        };
+        let mut buffer = self.buffer.borrow_mut();
+        buffer.clear();
+        // softbuffer expects 0RGB words, which is BGRA in little-endian byte order.
+        for row in pixels.chunks(pixmap.row_bytes()).take(height as usize) {
+            buffer.extend(
+                row[..width as usize * 4]
+                    .chunks_exact(4)
+                    .map(|bgra| u32::from_le_bytes([bgra[0], bgra[1], bgra[2], 0])),
+            );
+        }
+        self.graphics_context
+            .borrow_mut()
+            .set_buffer(&buffer, width as u16, height as u16);
+    }
+    pub(crate) fn begin_paint(&self) -> RefMut<skia_safe::Surface> {
+        let mut surface = self.surface.borrow_mut();
+        apply_coordinate_system(
+            &mut surface,
+            self.config.coordinate_system,
+            self.scale_factor(),
+        );
+        surface
+    }
+    /// Draws without copying the result to the window.
+    pub fn paint_no_present<F: FnOnce(&mut skia_safe::Canvas)>(&self, f: F) {
+        let mut surface = self.surface.borrow_mut();
+        apply_coordinate_system(
+            &mut surface,
+            self.config.coordinate_system,
+            self.scale_factor(),
+        );
+        f(surface.canvas());
+    }
+    pub fn snapshot(&self) -> skia_safe::Image {
+        self.surface.borrow_mut().image_snapshot()
+    }
+    pub fn read_pixels(&self, rect: skia_safe::IRect) -> Option<Vec<u8>> {
+        crate::read_surface_pixels(&mut self.surface.borrow_mut(), rect)
+    }
+    pub fn coordinate_system(&self) -> CoordinateSystem {
+        self.config.coordinate_system
+    }
+    pub fn request_repaint(&self) {
+        self.winit_window.request_redraw()
+    }
+    pub fn scale_factor(&self) -> f64 {
+        self.winit_window.scale_factor()
+    }
+    /// The scale is re-applied from the window at the start of every paint.
+    pub fn set_scale_factor(&self, _scale_factor: f64) {
+        self.winit_window.request_redraw();
+    }
+    pub fn window(&self) -> &winit::window::Window {
+        &self.winit_window
+    }
+}
+
+fn check_size(size: PhysicalSize<u32>) -> Result<(), RendererInitError> {
+    match check_surface_size(size, MAX_SURFACE_SIZE) {
+        Err(ResizeError::TooLarge { requested, max }) => {
+            Err(RendererInitError::TooLarge { requested, max })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// winit implements raw-window-handle 0.3 while softbuffer consumes 0.4, so the handle is
+/// translated once at construction. The window outlives the graphics context since both are
+/// owned by `RasterRenderer` and the context is declared, and so dropped, first.
+#[derive(Debug)]
+struct WindowHandle(raw_window_handle_04::RawWindowHandle);
+impl WindowHandle {
+    fn new(window: &winit::window::Window) -> Option<Self> {
+        use raw_window_handle::RawWindowHandle as Old;
+        use raw_window_handle_04::RawWindowHandle as New;
+
+        let handle = match window.raw_window_handle() {
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            ))]
+            Old::Xlib(old) => {
+                let mut new = raw_window_handle_04::XlibHandle::empty();
+                new.window = old.window;
+                new.display = old.display;
+                New::Xlib(new)
+            }
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "dragonfly",
+                target_os = "freebsd",
+                target_os = "netbsd",
+                target_os = "openbsd"
+            ))]
+            Old::Wayland(old) => {
+                let mut new = raw_window_handle_04::WaylandHandle::empty();
+                new.surface = old.surface;
+                new.display = old.display;
+                New::Wayland(new)
+            }
+            #[cfg(target_os = "windows")]
+            Old::Windows(old) => {
+                let mut new = raw_window_handle_04::Win32Handle::empty();
+                new.hwnd = old.hwnd;
+                new.hinstance = old.hinstance;
+                New::Win32(new)
+            }
+            #[cfg(target_os = "macos")]
+            Old::MacOS(old) => {
+                let mut new = raw_window_handle_04::AppKitHandle::empty();
+                new.ns_window = old.ns_window;
+                new.ns_view = old.ns_view;
+                New::AppKit(new)
+            }
+            _ => return None,
+        };
+        Some(Self(handle))
+    }
+}
+unsafe impl raw_window_handle_04::HasRawWindowHandle for WindowHandle {
+    fn raw_window_handle(&self) -> raw_window_handle_04::RawWindowHandle {
+        self.0
+    }
+}
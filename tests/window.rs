@@ -0,0 +1,201 @@
+//! These tests open real windows, so they need a display server and a GPU or software driver and
+//! are ignored by default. Run them with `cargo test -- --ignored --test-threads=1`.
+
+use skia_vulkan_gl_renderer::{skia_safe, winit, PaintError, WindowRenderer};
+use std::time::{Duration, Instant};
+use winit::{
+    dpi::{LogicalSize, PhysicalSize},
+    event::Event,
+    event_loop::{ControlFlow, EventLoop},
+    platform::desktop::EventLoopExtDesktop,
+};
+
+/// The test harness runs each test on its own thread, where winit only creates event loops when
+/// asked to explicitly.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+fn event_loop<T: 'static>() -> EventLoop<T> {
+    winit::platform::unix::EventLoopExtUnix::new_any_thread()
+}
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+)))]
+fn event_loop<T: 'static>() -> EventLoop<T> {
+    EventLoop::with_user_event()
+}
+
+fn renderer<T>(event_loop: &EventLoop<T>) -> WindowRenderer {
+    WindowRenderer::builder()
+        .title("test")
+        .inner_size(LogicalSize::new(320, 240))
+        .build(event_loop)
+        .unwrap()
+}
+
+/// Paint a frame, resizing and retrying once if the window changed size under the surface, like
+/// the examples do.
+fn paint_with(renderer: &WindowRenderer, mut draw: impl FnMut(&mut skia_safe::Canvas)) {
+    match renderer.paint(&mut draw) {
+        Err(PaintError::SurfaceOutOfDate) => {
+            renderer.resize(renderer.inner_size());
+            renderer.paint(&mut draw).unwrap();
+        }
+        result => result.unwrap(),
+    }
+}
+
+/// Run the event loop until `done` returns `true` or two seconds have passed, for window changes
+/// the window manager applies asynchronously. Returns the last `done`.
+fn pump_until<T>(event_loop: &mut EventLoop<T>, mut done: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(2);
+    event_loop.run_return(|event, _, control_flow| {
+        if let Event::MainEventsCleared = event {
+            *control_flow = if done() || Instant::now() >= deadline {
+                ControlFlow::Exit
+            } else {
+                ControlFlow::WaitUntil(Instant::now() + Duration::from_millis(10))
+            };
+        }
+    });
+    done()
+}
+
+fn paint(renderer: &WindowRenderer) {
+    paint_with(renderer, |canvas| {
+        canvas.clear(skia_safe::Color::WHITE);
+    });
+}
+
+#[test]
+#[ignore]
+fn resize_then_paint_twice() {
+    let event_loop = event_loop::<()>();
+    let renderer = renderer(&event_loop);
+    renderer
+        .window()
+        .set_inner_size(PhysicalSize::new(400, 300));
+    renderer.resize(renderer.inner_size());
+    paint(&renderer);
+    paint(&renderer);
+}
+
+#[test]
+#[ignore]
+fn fullscreen_round_trip_restores_size() {
+    let mut event_loop = event_loop::<()>();
+    let renderer = renderer(&event_loop);
+    let size = renderer.inner_size();
+    renderer.set_fullscreen(Some(winit::window::Fullscreen::Borderless(
+        renderer.current_monitor(),
+    )));
+    assert!(
+        pump_until(&mut event_loop, || renderer.inner_size() != size),
+        "the window never entered fullscreen"
+    );
+    paint(&renderer);
+    renderer.set_fullscreen(None);
+    assert!(pump_until(&mut event_loop, || renderer.inner_size() == size));
+    paint(&renderer);
+}
+
+enum UserEvent {
+    Redraw,
+}
+
+#[test]
+#[ignore]
+fn builds_with_a_custom_user_event() {
+    let event_loop = event_loop::<UserEvent>();
+    let renderer = renderer(&event_loop);
+    assert!(event_loop
+        .create_proxy()
+        .send_event(UserEvent::Redraw)
+        .is_ok());
+    paint(&renderer);
+}
+
+#[test]
+#[ignore]
+fn zero_size_resize_is_a_no_op() {
+    let event_loop = event_loop::<()>();
+    let renderer = renderer(&event_loop);
+    renderer.resize(PhysicalSize::new(0, 0));
+    assert!(renderer.try_resize(PhysicalSize::new(0, 0)).is_ok());
+    paint(&renderer);
+}
+
+#[test]
+#[ignore]
+fn paint_applies_the_content_scale() {
+    let event_loop = event_loop::<()>();
+    let renderer = renderer(&event_loop);
+    renderer.set_content_scale(1.5);
+    let expected = renderer.canvas_scale();
+    let mut scale = None;
+    paint_with(&renderer, |canvas| {
+        scale = Some(canvas.total_matrix().scale_x())
+    });
+    assert_eq!(scale, Some(expected));
+}
+
+#[test]
+#[ignore]
+fn resize_logical_round_trips_through_inner_size() {
+    for &scale_factor in &["1.0", "1.5"] {
+        // Only X11 reads the scale factor from the environment; elsewhere the display's is used.
+        std::env::set_var("WINIT_X11_SCALE_FACTOR", scale_factor);
+        let mut event_loop = event_loop::<()>();
+        let renderer = renderer(&event_loop);
+        let size = LogicalSize::new(400, 300);
+        renderer.resize_logical(size);
+        assert!(pump_until(&mut event_loop, || {
+            renderer
+                .inner_size()
+                .to_logical::<u32>(renderer.scale_factor())
+                == size
+        }));
+        paint(&renderer);
+    }
+    std::env::remove_var("WINIT_X11_SCALE_FACTOR");
+}
+
+#[test]
+#[ignore]
+fn surface_matches_inner_size_after_new() {
+    let event_loop = event_loop::<()>();
+    let renderer = renderer(&event_loop);
+    let size = renderer.inner_size();
+    // Skulpin sizes its swapchain from the window on every draw and doesn't expose it.
+    if let Some(image) = renderer.snapshot() {
+        assert_eq!(
+            (image.width() as u32, image.height() as u32),
+            (size.width, size.height)
+        );
+    }
+}
+
+#[test]
+#[ignore]
+fn paint_does_nothing_while_paused() {
+    let event_loop = event_loop::<()>();
+    let renderer = renderer(&event_loop);
+    renderer.set_paused(true);
+    let mut painted = false;
+    renderer.paint(|_| painted = true).unwrap();
+    assert!(!painted);
+    assert_eq!(renderer.frame_count(), 0);
+
+    renderer.set_paused(false);
+    paint_with(&renderer, |_| painted = true);
+    assert!(painted);
+    assert_eq!(renderer.frame_count(), 1);
+}